@@ -0,0 +1,218 @@
+//! A small syntax tree covering the parts of Python needed to describe
+//! classes: class and function definitions, (annotated) assignments and the
+//! expressions used in annotations, defaults and decorators.
+//!
+//! Every node carries the span of the source it was parsed from.
+
+use std::fmt;
+
+pub use crate::tokenizer::Span;
+
+#[derive(Debug, Clone, Default)]
+pub struct Module {
+    pub body: Vec<Stmt>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Stmt {
+    ClassDef(ClassDef),
+    FunctionDef(FunctionDef),
+    AnnAssign(AnnAssign),
+    Assign(Assign),
+    Import(Import),
+    ImportFrom(ImportFrom),
+    /// An expression statement, e.g., a docstring or a bare enum variant.
+    Expr(Expr),
+    Pass(Span),
+    /// Any statement not modelled by this tree (control flow, imports, ...).
+    Other(Span),
+}
+
+impl Stmt {
+    pub fn span(&self) -> Span {
+        match self {
+            Stmt::ClassDef(class) => class.span,
+            Stmt::FunctionDef(func) => func.span,
+            Stmt::AnnAssign(assign) => assign.span,
+            Stmt::Assign(assign) => assign.span,
+            Stmt::Import(import) => import.span,
+            Stmt::ImportFrom(import) => import.span,
+            Stmt::Expr(expr) => expr.span,
+            Stmt::Pass(span) | Stmt::Other(span) => *span,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ClassDef {
+    pub name: String,
+    pub decorators: Vec<Expr>,
+    pub bases: Vec<Expr>,
+    /// Keyword arguments in the base list, e.g., `metaclass=ABCMeta`.
+    pub keywords: Vec<Keyword>,
+    pub body: Vec<Stmt>,
+    pub span: Span,
+}
+
+/// A function definition. Statements in the function body are not parsed.
+#[derive(Debug, Clone)]
+pub struct FunctionDef {
+    pub name: String,
+    pub is_async: bool,
+    pub decorators: Vec<Expr>,
+    pub params: Vec<Param>,
+    pub returns: Option<Expr>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParamKind {
+    Regular,
+    /// `*args`
+    VarPositional,
+    /// `**kwargs`
+    VarKeyword,
+    /// The bare `*` separating keyword-only parameters.
+    KeywordOnlyMarker,
+    /// The bare `/` separating positional-only parameters.
+    PositionalOnlyMarker,
+}
+
+#[derive(Debug, Clone)]
+pub struct Param {
+    pub name: String,
+    pub kind: ParamKind,
+    pub annotation: Option<Expr>,
+    pub default: Option<Expr>,
+    pub span: Span,
+}
+
+/// `target: annotation [= value]`
+#[derive(Debug, Clone)]
+pub struct AnnAssign {
+    pub target: Expr,
+    pub annotation: Expr,
+    pub value: Option<Expr>,
+    pub span: Span,
+}
+
+/// `target [= target ...] = value`
+#[derive(Debug, Clone)]
+pub struct Assign {
+    pub targets: Vec<Expr>,
+    pub value: Expr,
+    pub span: Span,
+}
+
+/// `import a.b as c, d`
+#[derive(Debug, Clone)]
+pub struct Import {
+    pub names: Vec<Alias>,
+    pub span: Span,
+}
+
+/// `from .module import a as b, c`
+#[derive(Debug, Clone)]
+pub struct ImportFrom {
+    /// `None` for `from . import a`.
+    pub module: Option<String>,
+    pub names: Vec<Alias>,
+    /// Number of leading dots of a relative import.
+    pub level: usize,
+    pub span: Span,
+}
+
+/// A name bound by an import. `name` is `*` for `from module import *`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Alias {
+    pub name: String,
+    pub asname: Option<String>,
+}
+
+/// A keyword argument of a call or class definition. `arg` is `None` for
+/// `**mapping` unpacking.
+#[derive(Debug, Clone)]
+pub struct Keyword {
+    pub arg: Option<String>,
+    pub value: Expr,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct Expr {
+    pub kind: ExprKind,
+    /// The source text of the expression with whitespace normalized.
+    pub text: String,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub enum ExprKind {
+    Name(String),
+    /// A literal string, number, `True`, `False`, `None` or `...`.
+    Constant,
+    Attribute {
+        value: Box<Expr>,
+        attr: String,
+    },
+    /// `value[index]`; a tuple index such as `dict[str, int]` is flattened
+    /// into its elements.
+    Subscript {
+        value: Box<Expr>,
+        index: Vec<Expr>,
+    },
+    Call {
+        func: Box<Expr>,
+        args: Vec<Expr>,
+        keywords: Vec<Keyword>,
+    },
+    BinOp {
+        left: Box<Expr>,
+        op: String,
+        right: Box<Expr>,
+    },
+    UnaryOp {
+        op: String,
+        operand: Box<Expr>,
+    },
+    Tuple(Vec<Expr>),
+    List(Vec<Expr>),
+    Set(Vec<Expr>),
+    Dict(Vec<(Expr, Expr)>),
+    /// Any expression not modelled by this tree (lambdas, comprehensions,
+    /// conditional expressions, ...). Only its text is kept.
+    Other,
+}
+
+impl Expr {
+    /// Returns the dotted name of a `Name` or `Attribute` chain, e.g.,
+    /// `pydantic.BaseModel`.
+    pub fn dotted_name(&self) -> Option<String> {
+        match &self.kind {
+            ExprKind::Name(name) => Some(name.clone()),
+            ExprKind::Attribute { value, attr } => {
+                Some(format!("{}.{}", value.dotted_name()?, attr))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the contents of a plain string literal, e.g., `id` for
+    /// `"id"`. Byte strings and f-strings are not considered plain.
+    pub fn string_value(&self) -> Option<String> {
+        if !matches!(self.kind, ExprKind::Constant) {
+            return None;
+        }
+        let text = self.text.trim_start_matches(['r', 'R', 'u', 'U']);
+        ["\"\"\"", "'''", "\"", "'"].iter().find_map(|quote| {
+            let inner = text.strip_prefix(quote)?.strip_suffix(quote)?;
+            (!inner.contains(quote)).then(|| inner.to_string())
+        })
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
@@ -0,0 +1,287 @@
+use crate::{glob::Glob, utils::FileFilter};
+use nereus::{diagnostic::Severity, filter::ClassFilter, scanner::PyClassKind, Registry};
+use std::path::PathBuf;
+
+pub const USAGE: &str = "\
+Usage: nereus [OPTIONS] <PATH>
+
+Draws a class diagram of the Python classes found under PATH.
+
+Options:
+  -f, --format <FORMAT>    Output format: {formats} [default: inferred from the extension of
+                           the output file, or mermaid]
+  -o, --output <FILE>      Write the diagram to FILE, or to stdout if FILE is - [default: -]
+      --include <GLOB>     Scan files matching GLOB; may be repeated [default: *.py]
+      --exclude <GLOB>     Skip files and directories matching GLOB; may be repeated
+      --gitignore          Skip files and directories listed in .gitignore files
+      --lenient            Skip code that cannot be scanned with a warning instead of failing
+      --only <KIND>        Draw only classes of KIND: basemodel, dataclass, enum, typeddict,
+                           namedtuple or struct; may be repeated
+      --root <CLASS>       Draw only classes reachable from CLASS through base classes and field
+                           types; may be repeated
+      --depth <N>          Follow at most N base classes or field types from a root
+      --field-metadata     Show the metadata of pydantic fields
+      --validators         Show the validators and serializers applied to each field
+      --method-dependencies
+                           Draw dependencies on classes named in method signatures
+      --namespaces         Group classes by Python package (mermaid only)
+  -q, --quiet              Only report errors
+  -v, --verbose            Also report statements skipped while scanning
+  -h, --help               Print this help
+  -V, --version            Print the version";
+
+/// Where to write the diagram.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Output {
+    Stdout,
+    File(PathBuf),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Args {
+    pub path: PathBuf,
+    pub filter: FileFilter,
+    /// Which of the scanned classes to draw.
+    pub classes: ClassFilter,
+    /// Name of the output format, if given.
+    pub format: Option<String>,
+    pub output: Output,
+    /// Skip what cannot be scanned instead of failing.
+    pub lenient: bool,
+    /// Least severe diagnostics to report.
+    pub report: Severity,
+    pub options: nereus::RenderOptions,
+}
+
+impl Args {
+    /// Returns the name of the output format: the one given, else the one
+    /// whose extension the output file has, else mermaid.
+    pub fn format<'a>(&'a self, registry: &'a Registry) -> &'a str {
+        if let Some(format) = &self.format {
+            return format;
+        }
+        let extension = match &self.output {
+            Output::File(path) => path.extension().and_then(|ext| ext.to_str()),
+            Output::Stdout => None,
+        };
+        extension
+            .and_then(|ext| registry.find_by_extension(ext))
+            .unwrap_or("mermaid")
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Command {
+    Run(Args),
+    Help,
+    Version,
+}
+
+/// Parses the command line arguments, excluding the program name.
+pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Command, String> {
+    let mut args = args.into_iter();
+    let mut path = None;
+    let mut filter = FileFilter::default();
+    let mut include = vec![];
+    let mut classes = ClassFilter::default();
+    let mut format = None;
+    let mut output = Output::Stdout;
+    let mut lenient = false;
+    let mut report = Severity::Warning;
+    let mut options = nereus::RenderOptions::default();
+    while let Some(arg) = args.next() {
+        // Accept `--option=value` as well as `--option value`.
+        let (arg, inline_value) = match arg.split_once('=') {
+            Some((name, value)) if name.starts_with("--") => {
+                (name.to_string(), Some(value.to_string()))
+            }
+            _ => (arg, None),
+        };
+        let mut value = |name: &str| {
+            inline_value
+                .clone()
+                .or_else(|| args.next())
+                .ok_or_else(|| format!("Missing value for {name}"))
+        };
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "-V" | "--version" => return Ok(Command::Version),
+            "-f" | "--format" => format = Some(value(&arg)?),
+            "-o" | "--output" => {
+                output = match value(&arg)?.as_str() {
+                    "-" => Output::Stdout,
+                    file => Output::File(PathBuf::from(file)),
+                }
+            }
+            "--include" => include.push(Glob::new(&value(&arg)?)),
+            "--exclude" => filter.exclude.push(Glob::new(&value(&arg)?)),
+            "--gitignore" => filter.gitignore = true,
+            "--lenient" => lenient = true,
+            "--only" => classes.kinds.push(match value(&arg)?.as_str() {
+                "basemodel" => PyClassKind::Model,
+                "dataclass" => PyClassKind::Dataclass,
+                "enum" => PyClassKind::Enum,
+                "typeddict" => PyClassKind::TypedDict,
+                "namedtuple" => PyClassKind::NamedTuple,
+                "struct" => PyClassKind::Struct,
+                kind => return Err(format!("Unknown class kind: {kind}")),
+            }),
+            "--root" => classes.roots.push(value(&arg)?),
+            "--depth" => {
+                let depth = value(&arg)?;
+                match depth.parse() {
+                    Ok(depth) => classes.depth = Some(depth),
+                    Err(_) => return Err(format!("Invalid depth: {depth}")),
+                }
+            }
+            "--field-metadata" => options.field_metadata = true,
+            "--validators" => options.validators = true,
+            "--method-dependencies" => options.method_dependencies = true,
+            "--namespaces" => options.namespaces = true,
+            "-q" | "--quiet" => report = Severity::Error,
+            "-v" | "--verbose" => report = Severity::Note,
+            flag if flag.starts_with('-') && flag != "-" => {
+                return Err(format!("Unknown option: {flag}"))
+            }
+            _ if path.is_some() => return Err(format!("Unexpected argument: {arg}")),
+            _ => path = Some(PathBuf::from(arg)),
+        }
+    }
+    let path = path.ok_or("Missing path to .py files")?;
+    if classes.depth.is_some() && classes.roots.is_empty() {
+        return Err("--depth requires --root".to_string());
+    }
+    if !include.is_empty() {
+        filter.include = include;
+    }
+    Ok(Command::Run(Args {
+        path,
+        filter,
+        classes,
+        format,
+        output,
+        lenient,
+        report,
+        options,
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse_args(args: &[&str]) -> Result<Command, String> {
+        parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn test_parse() {
+        let Ok(Command::Run(args)) = parse_args(&[
+            "models",
+            "--format=plantuml",
+            "-o",
+            "out.puml",
+            "--field-metadata",
+            "--validators",
+        ]) else {
+            panic!("Failed to parse arguments");
+        };
+        assert_eq!(args.path, PathBuf::from("models"));
+        assert_eq!(args.format, Some("plantuml".to_string()));
+        assert_eq!(args.output, Output::File(PathBuf::from("out.puml")));
+        assert!(args.options.field_metadata);
+        assert!(args.options.validators);
+        assert!(!args.options.method_dependencies);
+
+        let Ok(Command::Run(args)) = parse_args(&["-o", "-", "models"]) else {
+            panic!("Failed to parse arguments");
+        };
+        assert_eq!(args.output, Output::Stdout);
+        assert_eq!(args.format, None);
+        assert_eq!(args.format(&Registry::default()), "mermaid");
+
+        let registry = Registry::default();
+        let Ok(Command::Run(args)) = parse_args(&["models", "-o", "classes.dot"]) else {
+            panic!("Failed to parse arguments");
+        };
+        assert_eq!(args.format(&registry), "dot");
+        let Ok(Command::Run(args)) = parse_args(&["models", "-o", "classes.txt"]) else {
+            panic!("Failed to parse arguments");
+        };
+        assert_eq!(args.format(&registry), "mermaid");
+        let Ok(Command::Run(args)) = parse_args(&["models", "-f", "json", "-o", "a.dot"]) else {
+            panic!("Failed to parse arguments");
+        };
+        assert_eq!(args.format(&registry), "json");
+        assert_eq!(args.filter, FileFilter::default());
+        assert!(!args.lenient);
+        assert_eq!(args.report, Severity::Warning);
+
+        let Ok(Command::Run(args)) = parse_args(&[
+            "models",
+            "--include",
+            "*.pyi",
+            "--exclude=tests",
+            "--gitignore",
+            "--lenient",
+            "-q",
+            "--only=basemodel",
+            "--only",
+            "enum",
+            "--root",
+            "Order",
+            "--depth",
+            "2",
+        ]) else {
+            panic!("Failed to parse arguments");
+        };
+        assert_eq!(args.filter.include, [Glob::new("*.pyi")]);
+        assert_eq!(args.filter.exclude, [Glob::new("tests")]);
+        assert!(args.filter.gitignore);
+        assert!(args.lenient);
+        assert_eq!(args.report, Severity::Error);
+        assert_eq!(
+            args.classes,
+            ClassFilter {
+                kinds: vec![PyClassKind::Model, PyClassKind::Enum],
+                roots: vec!["Order".to_string()],
+                depth: Some(2),
+            }
+        );
+
+        assert_eq!(parse_args(&["models", "--help"]), Ok(Command::Help));
+        assert_eq!(parse_args(&["-V"]), Ok(Command::Version));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            parse_args(&[]),
+            Err("Missing path to .py files".to_string())
+        );
+        assert_eq!(
+            parse_args(&["models", "-o"]),
+            Err("Missing value for -o".to_string())
+        );
+        assert_eq!(
+            parse_args(&["models", "--colour"]),
+            Err("Unknown option: --colour".to_string())
+        );
+        assert_eq!(
+            parse_args(&["models", "more"]),
+            Err("Unexpected argument: more".to_string())
+        );
+        assert_eq!(
+            parse_args(&["models", "--only", "protocol"]),
+            Err("Unknown class kind: protocol".to_string())
+        );
+        assert_eq!(
+            parse_args(&["models", "--root", "A", "--depth", "-1"]),
+            Err("Invalid depth: -1".to_string())
+        );
+        assert_eq!(
+            parse_args(&["models", "--depth", "1"]),
+            Err("--depth requires --root".to_string())
+        );
+    }
+}
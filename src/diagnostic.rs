@@ -0,0 +1,402 @@
+//! Problems found while scanning Python source, and their rustc-style
+//! rendering with the offending line highlighted.
+
+use crate::tokenizer::Span;
+use std::{
+    error::Error,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+/// Source that cannot be scanned.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScanError {
+    /// A bracket that is never closed.
+    UnterminatedBracket {
+        open: char,
+        span: Span,
+    },
+    /// A closing bracket without a matching opening one.
+    UnmatchedBracket {
+        close: char,
+        span: Span,
+    },
+    UnterminatedString {
+        span: Span,
+    },
+    /// A triple-quoted string, typically a docstring, that is never closed.
+    UnterminatedDocstring {
+        span: Span,
+    },
+    /// A class or function header that does not end in a colon.
+    MissingColon {
+        span: Span,
+    },
+    /// A `class` or `def` that is not followed by a name.
+    InvalidDeclaration {
+        span: Span,
+    },
+    /// A function header without a parameter list.
+    MissingParameters {
+        name: String,
+        span: Span,
+    },
+    UnexpectedCharacter {
+        ch: char,
+        span: Span,
+    },
+    /// A backslash that does not end its line.
+    InvalidContinuation {
+        span: Span,
+    },
+    /// Indentation whose meaning depends on the width of a tab.
+    InconsistentIndentation {
+        span: Span,
+    },
+    /// A dedent to a column that matches no enclosing block.
+    InvalidDedent {
+        span: Span,
+    },
+}
+
+impl ScanError {
+    pub fn span(&self) -> Span {
+        match self {
+            ScanError::UnterminatedBracket { span, .. }
+            | ScanError::UnmatchedBracket { span, .. }
+            | ScanError::UnterminatedString { span }
+            | ScanError::UnterminatedDocstring { span }
+            | ScanError::MissingColon { span }
+            | ScanError::InvalidDeclaration { span }
+            | ScanError::MissingParameters { span, .. }
+            | ScanError::UnexpectedCharacter { span, .. }
+            | ScanError::InvalidContinuation { span }
+            | ScanError::InconsistentIndentation { span }
+            | ScanError::InvalidDedent { span } => *span,
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            ScanError::UnterminatedBracket { open, .. } => {
+                format!("bracket '{}' was never closed", open)
+            }
+            ScanError::UnmatchedBracket { close, .. } => {
+                format!("unmatched closing bracket '{}'", close)
+            }
+            ScanError::UnterminatedString { .. } => "unterminated string literal".to_string(),
+            ScanError::UnterminatedDocstring { .. } => {
+                "unterminated triple-quoted string".to_string()
+            }
+            ScanError::MissingColon { .. } => "expected ':' at the end of the header".to_string(),
+            ScanError::InvalidDeclaration { .. } => {
+                "expected a name after 'class' or 'def'".to_string()
+            }
+            ScanError::MissingParameters { name, .. } => {
+                format!("expected a parameter list for function '{}'", name)
+            }
+            ScanError::UnexpectedCharacter { ch, .. } => format!("unexpected character '{}'", ch),
+            ScanError::InvalidContinuation { .. } => {
+                "unexpected character after line continuation".to_string()
+            }
+            ScanError::InconsistentIndentation { .. } => {
+                "inconsistent use of tabs and spaces in indentation".to_string()
+            }
+            ScanError::InvalidDedent { .. } => {
+                "unindent does not match any outer indentation level".to_string()
+            }
+        }
+    }
+
+    /// Describes the error as a diagnostic pointing into `source`.
+    pub fn to_diagnostic(&self, severity: Severity, source: &str) -> Diagnostic {
+        Diagnostic::new(severity, self.message()).at(self.span(), source)
+    }
+
+    /// Renders the error along with the line of `source` it occurred on.
+    pub fn render(&self, source: &str, file: Option<&Path>) -> String {
+        let diagnostic = self.to_diagnostic(Severity::Error, source);
+        match file {
+            Some(file) => diagnostic.in_file(file).to_string(),
+            None => diagnostic.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ScanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let span = self.span();
+        write!(
+            f,
+            "{} on line {}, column {}",
+            self.message(),
+            span.line,
+            span.col + 1
+        )
+    }
+}
+
+impl Error for ScanError {}
+
+/// How serious a diagnostic is, from most to least severe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning,
+    /// Something worth knowing that does not affect the result, e.g., a
+    /// statement the scanner does not model.
+    Note,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Note => write!(f, "note"),
+        }
+    }
+}
+
+/// A problem met while scanning, reported to the user rather than failing
+/// the scan.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub file: Option<PathBuf>,
+    pub snippet: Option<Snippet>,
+}
+
+/// The source line a diagnostic points at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snippet {
+    /// Span of the problem, with byte offsets relative to the start of `line`.
+    pub span: Span,
+    /// Text of the line the span starts on.
+    pub line: String,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, message: impl Into<String>) -> Diagnostic {
+        Diagnostic {
+            severity,
+            message: message.into(),
+            file: None,
+            snippet: None,
+        }
+    }
+
+    /// Points the diagnostic at `span` of `source`. Offsets past the end of
+    /// the line or within a character are moved back onto the line.
+    pub fn at(self, span: Span, source: &str) -> Diagnostic {
+        let boundary = |mut offset: usize| {
+            while !source.is_char_boundary(offset) {
+                offset -= 1;
+            }
+            offset
+        };
+        let start = boundary(span.start.min(source.len()));
+        let line_start = source[..start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = source[start..]
+            .find('\n')
+            .map_or(source.len(), |i| start + i);
+        let line = source[line_start..line_end].trim_end_matches('\r');
+        // Keep the span within the line, which is all that is shown.
+        let start = start.min(line_start + line.len());
+        let end = boundary(span.end.clamp(start, line_start + line.len()));
+        let span = Span {
+            start: start - line_start,
+            end: end - line_start,
+            ..span
+        };
+        Diagnostic {
+            snippet: Some(Snippet {
+                span,
+                line: line.to_string(),
+            }),
+            ..self
+        }
+    }
+
+    pub fn in_file(self, file: &Path) -> Diagnostic {
+        Diagnostic {
+            file: Some(file.to_path_buf()),
+            ..self
+        }
+    }
+}
+
+/// Formats the diagnostic in the style of rustc, e.g.,
+///
+/// ```text
+/// error: bracket '(' was never closed
+///  --> models.py:3:8
+///   |
+/// 3 | class A(:
+///   |        ^
+/// ```
+///
+/// Spans covering several lines are highlighted on their first line only.
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.severity, self.message)?;
+        let file = self
+            .file
+            .as_ref()
+            .map_or("<source>".to_string(), |file| file.display().to_string());
+        let Some(Snippet { span, line }) = &self.snippet else {
+            if self.file.is_some() {
+                write!(f, "\n --> {}", file)?;
+            }
+            return Ok(());
+        };
+        let number = span.line.to_string();
+        let gutter = " ".repeat(number.len());
+        let width = line
+            .get(span.start..span.end)
+            .map_or(0, |text| text.chars().count())
+            .max(1);
+        writeln!(f)?;
+        writeln!(f, "{}--> {}:{}:{}", gutter, file, span.line, span.col + 1)?;
+        writeln!(f, "{} |", gutter)?;
+        writeln!(f, "{} | {}", number, line)?;
+        write!(
+            f,
+            "{} | {}{}",
+            gutter,
+            " ".repeat(span.col),
+            "^".repeat(width)
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser;
+
+    fn render_error(source: &str) -> String {
+        parser::parse(source)
+            .unwrap_err()
+            .render(source, Some(Path::new("models.py")))
+    }
+
+    #[test]
+    fn test_render() {
+        assert_eq!(
+            render_error("import x\n\nclass A(pydantic.BaseModel\n    x: int\n"),
+            concat!(
+                "error: bracket '(' was never closed\n",
+                " --> models.py:3:8\n",
+                "  |\n",
+                "3 | class A(pydantic.BaseModel\n",
+                "  |        ^",
+            )
+        );
+        assert_eq!(
+            render_error("class A:\n    '''Docstring\n    x: int\n"),
+            concat!(
+                "error: unterminated triple-quoted string\n",
+                " --> models.py:2:5\n",
+                "  |\n",
+                "2 |     '''Docstring\n",
+                "  |     ^^^^^^^^^^^^",
+            )
+        );
+    }
+
+    #[test]
+    fn test_diagnostic() {
+        let source = "class A:\r\n    x = [1, 2)\r\n";
+        let (_, errors) = parser::parse_lenient(source);
+        let diagnostic = errors[0]
+            .to_diagnostic(Severity::Warning, source)
+            .in_file(Path::new("a.py"));
+        assert_eq!(
+            diagnostic.to_string(),
+            concat!(
+                "warning: unmatched closing bracket ')'\n",
+                " --> a.py:2:14\n",
+                "  |\n",
+                "2 |     x = [1, 2)\n",
+                "  |              ^",
+            )
+        );
+        // Spans on the line break itself, or past the end of the source.
+        let source = "x = 'é'\r\ny = 1";
+        let span = Span {
+            start: 8,
+            end: 10,
+            line: 1,
+            col: 7,
+        };
+        let diagnostic = Diagnostic::new(Severity::Error, "here").at(span, source);
+        let snippet = diagnostic.snippet.as_ref().unwrap();
+        assert_eq!(snippet.line, "x = 'é'");
+        assert_eq!((snippet.span.start, snippet.span.end), (8, 8));
+        assert!(diagnostic
+            .to_string()
+            .ends_with("1 | x = 'é'\n  |        ^"));
+        let span = Span {
+            start: 6,
+            end: 100,
+            line: 1,
+            col: 5,
+        };
+        let diagnostic = Diagnostic::new(Severity::Error, "here").at(span, source);
+        let snippet = diagnostic.snippet.unwrap();
+        assert_eq!((snippet.span.start, snippet.span.end), (5, 8));
+        let span = Span {
+            start: 100,
+            end: 100,
+            line: 2,
+            col: 5,
+        };
+        let diagnostic = Diagnostic::new(Severity::Error, "here").at(span, source);
+        assert_eq!(diagnostic.snippet.unwrap().line, "y = 1");
+
+        let diagnostic = Diagnostic::new(Severity::Warning, "stream did not contain valid UTF-8");
+        assert_eq!(
+            diagnostic.in_file(Path::new("a.py")).to_string(),
+            "warning: stream did not contain valid UTF-8\n --> a.py"
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = |source: &str| parser::parse(source).unwrap_err();
+        assert!(matches!(
+            error("class A\n    pass\n"),
+            ScanError::MissingColon { .. }
+        ));
+        assert!(matches!(
+            error("class A:\n    def f(self) -> None\n"),
+            ScanError::MissingColon { .. }
+        ));
+        assert!(matches!(
+            error("class A:\n    def f:\n        pass\n"),
+            ScanError::MissingParameters { .. }
+        ));
+        assert!(matches!(
+            error("class :\n    pass\n"),
+            ScanError::InvalidDeclaration { .. }
+        ));
+        assert!(matches!(
+            error("x = [1, 2)\n"),
+            ScanError::UnmatchedBracket { close: ')', .. }
+        ));
+        assert!(matches!(
+            error("x = 'abc\n"),
+            ScanError::UnterminatedString { .. }
+        ));
+        assert!(matches!(
+            error("x = 1 $ 2\n"),
+            ScanError::UnexpectedCharacter { ch: '$', .. }
+        ));
+        assert_eq!(
+            error("a:\n    b\n  c\n").to_string(),
+            "unindent does not match any outer indentation level on line 3, column 1"
+        );
+    }
+}
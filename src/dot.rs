@@ -0,0 +1,266 @@
+use crate::{
+    consts,
+    relations::{self, ClassIndex, Edge, Relation, RelationKind},
+    scanner::{PyAttribute, PyClass, PyMethodModifier, SignatureStyle},
+    RenderOptions, Renderer,
+};
+use std::{error::Error, io};
+
+pub struct ClassDiagram;
+impl ClassDiagram {
+    pub fn make(
+        models: &[PyClass],
+        options: &RenderOptions,
+        lines: &mut Vec<String>,
+    ) -> Result<(), &'static str> {
+        lines.push("digraph classes {".to_string());
+        lines.push(format!("{}rankdir=BT;", consts::INDENT));
+        lines.push(format!("{}node [shape=record];", consts::INDENT));
+        let index = ClassIndex::new(models);
+
+        // Classes of the same Python module are laid out in one cluster, in
+        // the order the modules were first seen.
+        for (module, group) in relations::group_by(models, |m| m.module.clone()) {
+            let indent = match &module {
+                Some(module) => {
+                    lines.push(format!(
+                        "{}subgraph {} {{",
+                        consts::INDENT,
+                        quote(&format!("cluster_{}", module))
+                    ));
+                    lines.push(format!(
+                        "{}{}label={};",
+                        consts::INDENT,
+                        consts::INDENT,
+                        quote(module)
+                    ));
+                    format!("{}{}", consts::INDENT, consts::INDENT)
+                }
+                None => consts::INDENT.to_string(),
+            };
+            for model in group {
+                Self::make_class(model, &index, &indent, lines);
+            }
+            if module.is_some() {
+                lines.push(format!("{}}}", consts::INDENT));
+            }
+        }
+
+        for model in models.iter() {
+            for edge in relations::edges(model, &index) {
+                lines.push(Self::make_edge(&edge));
+            }
+        }
+        for relation in relations::relations(models, options).iter() {
+            lines.push(Self::make_relation(relation));
+        }
+        lines.push("}".to_string());
+        Ok(())
+    }
+
+    fn make_class(model: &PyClass, index: &ClassIndex, indent: &str, lines: &mut Vec<String>) {
+        let id = index.id(model);
+        let mut header = escape(&id);
+        if let Some(stereotype) = index.kind(model).stereotype() {
+            header.insert_str(0, &format!("«{}»\\n", stereotype));
+        }
+
+        // Each field and method is left-aligned on its own line of the record.
+        let mut fields = String::new();
+        for member in model.members.iter() {
+            fields.push_str(&escape(&member.to_string()));
+            fields.push_str("\\l");
+        }
+        for attribute in model.attributes() {
+            let mut field = format!("{}{}", attribute.access().symbol(), attribute.name());
+            if let Some(type_) = attribute.type_() {
+                field.push_str(&format!(" : {}", type_));
+            }
+            if let PyAttribute::Field(prop) = attribute {
+                if let Some(default) = prop.default_text() {
+                    field.push_str(&format!(" = {}", default));
+                }
+            }
+            fields.push_str(&escape(&field));
+            fields.push_str("\\l");
+        }
+        let mut methods = String::new();
+        for method in model.drawn_methods() {
+            let modifier = match method.modifier() {
+                Some(PyMethodModifier::Static) => "{static} ",
+                Some(PyMethodModifier::Abstract) => "{abstract} ",
+                None => "",
+            };
+            let signature = format!(
+                "{}{}{}",
+                modifier,
+                method.access.symbol(),
+                method.signature(&SignatureStyle::UML)
+            );
+            methods.push_str(&escape(&signature));
+            methods.push_str("\\l");
+        }
+
+        // The label is already escaped, so that its line breaks (`\l`) are kept.
+        lines.push(format!(
+            "{}{} [label=\"{{{}|{}|{}}}\"];",
+            indent,
+            quote(&id),
+            header,
+            fields,
+            methods
+        ));
+        for child in model.children.iter() {
+            Self::make_class(child, index, indent, lines);
+        }
+    }
+
+    fn make_edge(edge: &Edge) -> String {
+        match edge {
+            Edge::Inherits { child, parent } => format!(
+                "{}{} -> {} [arrowhead=empty];",
+                consts::INDENT,
+                quote(child),
+                quote(parent)
+            ),
+            Edge::Nests { outer, inner } => format!(
+                "{}{} -> {} [arrowhead=odot, label=\"nested\"];",
+                consts::INDENT,
+                quote(inner),
+                quote(outer)
+            ),
+        }
+    }
+
+    fn make_relation(relation: &Relation) -> String {
+        let mut attributes = match relation.kind {
+            RelationKind::Composition => {
+                vec!["dir=back".to_string(), "arrowtail=diamond".to_string()]
+            }
+            RelationKind::Association => vec!["arrowhead=vee".to_string()],
+            RelationKind::Dependency => {
+                vec!["style=dashed".to_string(), "arrowhead=vee".to_string()]
+            }
+        };
+        if let Some(cardinality) = relation.cardinality {
+            attributes.push(format!("headlabel={}", quote(&cardinality.to_string())));
+        }
+        if let Some(label) = &relation.label {
+            attributes.push(format!("label={}", quote(label)));
+        }
+        format!(
+            "{}{} -> {} [{}];",
+            consts::INDENT,
+            quote(&relation.source),
+            quote(&relation.target),
+            attributes.join(", ")
+        )
+    }
+}
+
+impl Renderer for ClassDiagram {
+    fn extension(&self) -> &str {
+        "dot"
+    }
+
+    fn render(
+        &self,
+        models: &[PyClass],
+        options: &RenderOptions,
+        out: &mut dyn io::Write,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut lines = vec![];
+        Self::make(models, options, &mut lines)?;
+        write!(out, "{}", lines.join("\r\n"))?;
+        Ok(())
+    }
+}
+
+/// Quotes a DOT identifier or attribute value.
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Escapes the characters with a special meaning in record labels, e.g., the
+/// braces and pipes of `dict[str, int | None]`, as well as quotes and
+/// backslashes.
+fn escape(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        if matches!(c, '{' | '}' | '|' | '<' | '>' | '"' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::scanner;
+
+    fn render(source: &str) -> Vec<String> {
+        let mut lines = vec![];
+        let models = scanner::lex(source.to_string()).unwrap();
+        ClassDiagram::make(&models, &RenderOptions::default(), &mut lines).unwrap();
+        lines
+            .into_iter()
+            .map(|line| line.trim().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_record_nodes() {
+        let lines = render(concat!(
+            "class Item(pydantic.BaseModel):\n",
+            "    tags: dict[str, int | None] = {}\n",
+            "    def _check(self) -> bool: ...\n",
+        ));
+        assert_eq!(
+            lines[3],
+            concat!(
+                "\"Item\" [label=\"{«BaseModel»\\nItem",
+                "|+tags : dict[str, int \\| None] = \\{\\}\\l",
+                "|-_check() : bool\\l}\"];"
+            )
+        );
+    }
+
+    #[test]
+    fn test_edges() {
+        let lines = render(concat!(
+            "class Item(Base):\n",
+            "    class Meta:\n",
+            "        pass\n",
+            "class Order:\n",
+            "    items: list[Item]\n",
+        ));
+        assert!(lines.contains(&"\"Item\" -> \"Base\" [arrowhead=empty];".to_string()));
+        assert!(lines.contains(
+            &"\"Item.Meta\" -> \"Item\" [arrowhead=odot, label=\"nested\"];".to_string()
+        ));
+        assert!(lines.contains(
+            &"\"Order\" -> \"Item\" [dir=back, arrowtail=diamond, headlabel=\"*\", label=\"items\"];"
+                .to_string()
+        ));
+    }
+
+    #[test]
+    fn test_clusters() {
+        let mut models = scanner::lex("class A: ...\nclass B: ...\n".to_string()).unwrap();
+        models[1].module = Some("shop.models".to_string());
+        let mut lines = vec![];
+        ClassDiagram::make(&models, &RenderOptions::default(), &mut lines).unwrap();
+        assert_eq!(
+            lines[3..8],
+            [
+                "    \"A\" [label=\"{A||}\"];",
+                "    subgraph \"cluster_shop.models\" {",
+                "        label=\"shop.models\";",
+                "        \"B\" [label=\"{B||}\"];",
+                "    }",
+            ]
+        );
+    }
+}
@@ -0,0 +1,177 @@
+//! Selection of the scanned classes to draw, so that a diagram can focus on
+//! the models of interest.
+
+use crate::{
+    relations::ClassIndex,
+    scanner::{PyClass, PyClassKind},
+};
+use std::collections::{HashSet, VecDeque};
+
+/// Which scanned classes to keep in a diagram. The default keeps them all.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ClassFilter {
+    /// Keep only classes of these kinds, following base classes, e.g., a
+    /// subclass of a pydantic model is a model too. Empty to keep every kind.
+    pub kinds: Vec<PyClassKind>,
+    /// Keep only the classes reachable from these through base classes and
+    /// field types. Empty to keep every class.
+    pub roots: Vec<String>,
+    /// Follow at most this many base classes or field types from a root.
+    /// Unlimited if `None`.
+    pub depth: Option<usize>,
+}
+
+impl ClassFilter {
+    /// Returns the classes to keep. Nested classes are kept with their outer
+    /// class, or on their own if the outer class is dropped.
+    pub fn apply(&self, models: &[PyClass]) -> Result<Vec<PyClass>, String> {
+        let index = ClassIndex::new(models);
+        let reachable = if self.roots.is_empty() {
+            None
+        } else {
+            Some(self.reachable(&index)?)
+        };
+        let keep = |model: &PyClass| {
+            (self.kinds.is_empty() || self.kinds.contains(&index.kind(model)))
+                && reachable
+                    .as_ref()
+                    .is_none_or(|reachable| reachable.contains(&index.id(model)))
+        };
+        let mut kept = vec![];
+        select(models, &keep, &mut kept);
+        Ok(kept)
+    }
+
+    /// Collects the ids of the classes within `depth` steps of a root.
+    fn reachable(&self, index: &ClassIndex) -> Result<HashSet<String>, String> {
+        let mut queue = VecDeque::new();
+        for root in self.roots.iter() {
+            let model = index
+                .resolve(root)
+                .ok_or_else(|| format!("Unknown root class: {}", root))?;
+            queue.push_back((model, 0));
+        }
+
+        // Classes are visited breadth first, i.e., by their shortest path
+        // from a root.
+        let mut seen = HashSet::new();
+        while let Some((model, depth)) = queue.pop_front() {
+            if !seen.insert(index.id(model)) || self.depth.is_some_and(|max| depth >= max) {
+                continue;
+            }
+            let annotations = model.props.iter().filter_map(|prop| prop.type_.as_ref());
+            for expr in model.parents.iter().chain(annotations) {
+                for (id, _) in index.references(model, expr) {
+                    if let Some(next) = index.resolve(&id) {
+                        queue.push_back((next, depth + 1));
+                    }
+                }
+            }
+        }
+        Ok(seen)
+    }
+}
+
+fn select(models: &[PyClass], keep: &dyn Fn(&PyClass) -> bool, kept: &mut Vec<PyClass>) {
+    for model in models.iter() {
+        if keep(model) {
+            let mut children = vec![];
+            select(&model.children, keep, &mut children);
+            kept.push(PyClass {
+                children,
+                ..model.clone()
+            });
+        } else {
+            select(&model.children, keep, kept);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::scanner;
+
+    fn names(filter: &ClassFilter) -> Vec<String> {
+        let models = scanner::lex(
+            concat!(
+                "class Base(pydantic.BaseModel): ...\n",
+                "class Color(enum.Enum):\n",
+                "    RED = 'red'\n",
+                "@dataclass\n",
+                "class Point:\n",
+                "    x: int\n",
+                "class Line(Base):\n",
+                "    points: list[Point]\n",
+                "    color: Color\n",
+                "class Order(Base):\n",
+                "    lines: list[Line]\n",
+                "    class Meta:\n",
+                "        pass\n",
+                "class NotModel: ...\n",
+            )
+            .to_string(),
+        )
+        .unwrap();
+        fn collect(models: &[PyClass], names: &mut Vec<String>) {
+            for model in models.iter() {
+                names.push(model.qualname.clone());
+                collect(&model.children, names);
+            }
+        }
+        let mut found = vec![];
+        collect(&filter.apply(&models).unwrap(), &mut found);
+        found
+    }
+
+    #[test]
+    fn test_kinds() {
+        let filter = ClassFilter {
+            kinds: vec![PyClassKind::Model],
+            ..ClassFilter::default()
+        };
+        assert_eq!(names(&filter), ["Base", "Line", "Order"]);
+        let filter = ClassFilter {
+            kinds: vec![PyClassKind::Dataclass, PyClassKind::Enum],
+            ..ClassFilter::default()
+        };
+        assert_eq!(names(&filter), ["Color", "Point"]);
+        // Nested classes outlive their outer class.
+        let filter = ClassFilter {
+            kinds: vec![PyClassKind::Plain],
+            ..ClassFilter::default()
+        };
+        assert_eq!(names(&filter), ["Order.Meta", "NotModel"]);
+    }
+
+    #[test]
+    fn test_roots() {
+        let filter = ClassFilter {
+            roots: vec!["Line".to_string()],
+            ..ClassFilter::default()
+        };
+        assert_eq!(names(&filter), ["Base", "Color", "Point", "Line"]);
+        let filter = ClassFilter {
+            roots: vec!["Order".to_string()],
+            depth: Some(1),
+            ..ClassFilter::default()
+        };
+        assert_eq!(names(&filter), ["Base", "Line", "Order"]);
+        let filter = ClassFilter {
+            roots: vec!["Order".to_string()],
+            depth: Some(0),
+            kinds: vec![PyClassKind::Model],
+        };
+        assert_eq!(names(&filter), ["Order"]);
+
+        let filter = ClassFilter {
+            roots: vec!["Missing".to_string()],
+            ..ClassFilter::default()
+        };
+        let models = scanner::lex("class A: ...\n".to_string()).unwrap();
+        assert_eq!(
+            filter.apply(&models).unwrap_err(),
+            "Unknown root class: Missing"
+        );
+    }
+}
@@ -0,0 +1,141 @@
+/// A shell-style pattern over `/`-separated relative paths.
+///
+/// `*` matches within a path segment, `**` across segments, `?` a single
+/// character and `[a-z]` (or `[!a-z]`) a character class. Patterns without a
+/// `/` are matched against the file name only, so that `*.py` selects Python
+/// files at any depth.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Glob {
+    pattern: Vec<char>,
+    anchored: bool,
+}
+
+impl Glob {
+    pub fn new(pattern: &str) -> Glob {
+        match pattern.strip_prefix('/') {
+            // A leading slash anchors the pattern without being part of it.
+            Some(rest) => Glob::anchored(rest),
+            None => Glob {
+                pattern: pattern.chars().collect(),
+                anchored: pattern.contains('/'),
+            },
+        }
+    }
+
+    /// Creates a pattern that is matched against the whole path, even if it
+    /// does not contain a `/`.
+    pub fn anchored(pattern: &str) -> Glob {
+        Glob {
+            pattern: pattern.chars().collect(),
+            anchored: true,
+        }
+    }
+
+    pub fn matches(&self, path: &str) -> bool {
+        let path = if self.anchored {
+            path
+        } else {
+            path.rsplit('/').next().unwrap_or(path)
+        };
+        let path = path.chars().collect::<Vec<char>>();
+        matches(&self.pattern, &path)
+    }
+}
+
+fn matches(pattern: &[char], path: &[char]) -> bool {
+    match pattern {
+        [] => path.is_empty(),
+        ['*', '*', '/', rest @ ..] => {
+            // Zero or more whole directories.
+            matches(rest, path)
+                || (0..path.len()).any(|i| path[i] == '/' && matches(rest, &path[i + 1..]))
+        }
+        ['*', '*', rest @ ..] => (0..=path.len()).any(|i| matches(rest, &path[i..])),
+        ['*', rest @ ..] => {
+            let segment = path.iter().position(|c| *c == '/').unwrap_or(path.len());
+            (0..=segment).any(|i| matches(rest, &path[i..]))
+        }
+        ['?', rest @ ..] => !path.is_empty() && path[0] != '/' && matches(rest, &path[1..]),
+        ['[', rest @ ..] => match CharClass::parse(rest) {
+            Some((class, rest)) => match path.first() {
+                Some(c) if *c != '/' => class.contains(*c) && matches(rest, &path[1..]),
+                _ => false,
+            },
+            // An unterminated class is a literal `[`.
+            None => path.first() == Some(&'[') && matches(rest, &path[1..]),
+        },
+        [c, rest @ ..] => path.first() == Some(c) && matches(rest, &path[1..]),
+    }
+}
+
+/// A bracketed set of characters, e.g., `[a-z_]`.
+struct CharClass {
+    negated: bool,
+    ranges: Vec<(char, char)>,
+}
+
+impl CharClass {
+    /// Parses a class following its opening `[`, returning it along with the
+    /// rest of the pattern.
+    fn parse(pattern: &[char]) -> Option<(CharClass, &[char])> {
+        let (negated, mut pattern) = match pattern {
+            ['!' | '^', rest @ ..] => (true, rest),
+            _ => (false, pattern),
+        };
+        let mut ranges = vec![];
+        // A `]` right after the opening bracket is part of the class.
+        if let [']', rest @ ..] = pattern {
+            ranges.push((']', ']'));
+            pattern = rest;
+        }
+        loop {
+            match pattern {
+                [] => return None,
+                [']', rest @ ..] => return Some((CharClass { negated, ranges }, rest)),
+                [lo, '-', hi, rest @ ..] if *hi != ']' => {
+                    ranges.push((*lo, *hi));
+                    pattern = rest;
+                }
+                [c, rest @ ..] => {
+                    ranges.push((*c, *c));
+                    pattern = rest;
+                }
+            }
+        }
+    }
+
+    fn contains(&self, c: char) -> bool {
+        let found = self.ranges.iter().any(|(lo, hi)| *lo <= c && c <= *hi);
+        found != self.negated
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_file_name_patterns() {
+        let glob = Glob::new("*.py");
+        assert!(glob.matches("models.py"));
+        assert!(glob.matches("shop/models.py"));
+        assert!(!glob.matches("shop/models.pyc"));
+        assert!(Glob::new("test_?.py").matches("tests/test_a.py"));
+        assert!(Glob::new("[!_]*.py").matches("models.py"));
+        assert!(!Glob::new("[!_]*.py").matches("__init__.py"));
+        assert!(Glob::new("v[0-9].py").matches("v2.py"));
+    }
+
+    #[test]
+    fn test_path_patterns() {
+        assert!(Glob::new("shop/*.py").matches("shop/models.py"));
+        assert!(!Glob::new("shop/*.py").matches("shop/orders/models.py"));
+        assert!(Glob::new("shop/**/*.py").matches("shop/models.py"));
+        assert!(Glob::new("shop/**/*.py").matches("shop/orders/models.py"));
+        assert!(Glob::new("**/tests").matches("shop/tests"));
+        assert!(Glob::new("/build").matches("build"));
+        assert!(!Glob::new("/build").matches("shop/build"));
+        assert!(Glob::anchored("build").matches("build"));
+        assert!(!Glob::anchored("build").matches("shop/build"));
+    }
+}
@@ -0,0 +1,461 @@
+//! A JSON representation of scanned classes, for tools that want nereus's
+//! parse results rather than a diagram.
+//!
+//! The document has the following shape. Python expressions (types,
+//! defaults, decorators, ...) are given as their source text, and `line` is
+//! the 1-based line the construct starts on.
+//!
+//! ```text
+//! {
+//!     "version": 2,
+//!     "classes": [Class, ...]
+//! }
+//!
+//! Class = {
+//!     "name": string,
+//!     "qualname": string,           // e.g., "Outer.Inner"
+//!     "module": string | null,      // e.g., "shop.models"
+//!     "file": string | null,
+//!     "line": number,
+//!     "kind": "model" | "dataclass" | "enum" | "typed_dict" | "named_tuple"
+//!             | "struct" | "plain",
+//!     "decorators": [string, ...],
+//!     "parents": [string, ...],
+//!     "bases": [string, ...],       // parents resolved through imports
+//!     "fields": [Field, ...],
+//!     "members": [Member, ...],     // of an enumeration
+//!     "methods": [Method, ...],
+//!     "config": {string: string, ...}, // see below
+//!     "classes": [Class, ...]       // nested classes
+//! }
+//!
+//! Field = {
+//!     "name": string,
+//!     "type": string | null,
+//!     "default": string | null,
+//!     "default_factory": string | null,
+//!     "kind": "required" | "optional" | "defaulted",
+//!     "metadata": {string: string, ...},  // arguments of `Field(...)`
+//!     "line": number
+//! }
+//!
+//! Member = {
+//!     "name": string,
+//!     "value": string | null,
+//!     "line": number
+//! }
+//!
+//! Method = {
+//!     "name": string,
+//!     "access": "public" | "private",
+//!     "kind": "instance" | "class" | "static" | "property" | "property_accessor",
+//!     "decorators": [string, ...],
+//!     "hook": Hook | null,          // set for pydantic validators, ...
+//!     "params": [Param, ...],       // including `self` or `cls`
+//!     "returns": string | null,
+//!     "line": number
+//! }
+//!
+//! Hook = {
+//!     "kind": "validator" | "field_validator" | "model_validator"
+//!           | "field_serializer" | "model_serializer" | "computed_field",
+//!     "fields": [string, ...]       // `*` for all fields
+//! }
+//!
+//! Param = {
+//!     "name": string,               // prefixed with `*` or `**` if variadic
+//!     "type": string | null,
+//!     "default": string | null
+//! }
+//! ```
+//!
+//! An option set both in the class header, e.g., `class A(BaseModel,
+//! frozen=True)`, and in its body, e.g., `model_config = ConfigDict(...)`,
+//! appears once in `config`, with the value from the body, as that is the
+//! one pydantic applies.
+//!
+//! `version` is increased whenever a key is removed or changes meaning;
+//! consumers should ignore keys they do not know.
+
+use crate::{
+    ast::Expr,
+    relations::ClassIndex,
+    scanner::{
+        PyClass, PyClassKind, PyEnumMember, PyFieldKind, PyHookKind, PyMethod, PyMethodAccess,
+        PyMethodKind, PyParam,
+    },
+    RenderOptions, Renderer,
+};
+use std::{error::Error, fmt, io};
+
+/// Version of the document layout described in the module documentation.
+pub const SCHEMA_VERSION: usize = 2;
+
+/// Serializes scanned classes to a JSON document.
+pub fn to_json(models: &[PyClass]) -> String {
+    let index = ClassIndex::new(models);
+    let document = Value::Object(vec![
+        ("version", Value::Number(SCHEMA_VERSION)),
+        (
+            "classes",
+            Value::Array(models.iter().map(|m| class(m, &index)).collect()),
+        ),
+    ]);
+    document.to_string()
+}
+
+pub struct Json;
+
+impl Renderer for Json {
+    fn extension(&self) -> &str {
+        "json"
+    }
+
+    fn render(
+        &self,
+        models: &[PyClass],
+        _options: &RenderOptions,
+        out: &mut dyn io::Write,
+    ) -> Result<(), Box<dyn Error>> {
+        write!(out, "{}", to_json(models))?;
+        Ok(())
+    }
+}
+
+fn class<'a>(model: &'a PyClass, index: &ClassIndex) -> Value<'a> {
+    let kind = match index.kind(model) {
+        PyClassKind::Model => "model",
+        PyClassKind::Dataclass => "dataclass",
+        PyClassKind::Enum => "enum",
+        PyClassKind::TypedDict => "typed_dict",
+        PyClassKind::NamedTuple => "named_tuple",
+        PyClassKind::Struct => "struct",
+        PyClassKind::Plain => "plain",
+    };
+    let file = model
+        .file
+        .as_ref()
+        .map(|file| file.to_string_lossy().to_string());
+    // Options of the class body come after those of its header, so later
+    // entries take precedence.
+    let mut config: Vec<(&str, Value)> = vec![];
+    for entry in model.config.iter() {
+        let value = expr(&entry.value);
+        match config.iter_mut().find(|(key, _)| *key == entry.key) {
+            Some((_, old)) => *old = value,
+            None => config.push((&entry.key, value)),
+        }
+    }
+    Value::Object(vec![
+        ("name", Value::String(model.name.clone())),
+        ("qualname", Value::String(model.qualname.clone())),
+        ("module", optional(model.module.clone())),
+        ("file", optional(file)),
+        ("line", Value::Number(model.span.line)),
+        ("kind", Value::String(kind.to_string())),
+        (
+            "decorators",
+            Value::Array(model.decorators.iter().map(expr).collect()),
+        ),
+        (
+            "parents",
+            Value::Array(model.parents.iter().map(expr).collect()),
+        ),
+        (
+            "bases",
+            Value::Array(
+                model
+                    .parents
+                    .iter()
+                    .map(|parent| match parent.dotted_name() {
+                        Some(name) => Value::String(model.qualify(&name)),
+                        None => expr(parent),
+                    })
+                    .collect(),
+            ),
+        ),
+        (
+            "fields",
+            Value::Array(model.props.iter().map(field).collect()),
+        ),
+        (
+            "members",
+            Value::Array(model.members.iter().map(member).collect()),
+        ),
+        (
+            "methods",
+            Value::Array(model.methods.iter().map(method).collect()),
+        ),
+        ("config", Value::Object(config)),
+        (
+            "classes",
+            Value::Array(model.children.iter().map(|c| class(c, index)).collect()),
+        ),
+    ])
+}
+
+fn field(prop: &PyParam) -> Value<'_> {
+    let kind = match prop.kind() {
+        PyFieldKind::Required => "required",
+        PyFieldKind::Optional => "optional",
+        PyFieldKind::Defaulted => "defaulted",
+    };
+    let metadata = prop
+        .field
+        .iter()
+        .flat_map(|field| field.entries())
+        .map(|(key, value)| (key, expr(value)))
+        .collect();
+    Value::Object(vec![
+        ("name", Value::String(prop.name.clone())),
+        ("type", optional_expr(prop.type_.as_ref())),
+        ("default", optional_expr(prop.default_value())),
+        ("default_factory", optional_expr(prop.default_factory())),
+        ("kind", Value::String(kind.to_string())),
+        ("metadata", Value::Object(metadata)),
+        ("line", Value::Number(prop.span.line)),
+    ])
+}
+
+fn member(member: &PyEnumMember) -> Value<'_> {
+    Value::Object(vec![
+        ("name", Value::String(member.name.clone())),
+        ("value", optional_expr(member.value.as_ref())),
+        ("line", Value::Number(member.span.line)),
+    ])
+}
+
+fn method(method: &PyMethod) -> Value<'_> {
+    let access = match method.access {
+        PyMethodAccess::Public => "public",
+        PyMethodAccess::Private => "private",
+    };
+    let kind = match method.kind() {
+        PyMethodKind::Instance => "instance",
+        PyMethodKind::Class => "class",
+        PyMethodKind::Static => "static",
+        PyMethodKind::Property => "property",
+        PyMethodKind::PropertyAccessor => "property_accessor",
+    };
+    let params = method
+        .params
+        .iter()
+        .map(|param| {
+            Value::Object(vec![
+                ("name", Value::String(param.name.clone())),
+                ("type", optional_expr(param.type_.as_ref())),
+                ("default", optional_expr(param.default.as_ref())),
+            ])
+        })
+        .collect();
+    let hook = method.hook().map(|hook| {
+        let kind = match hook.kind {
+            PyHookKind::Validator => "validator",
+            PyHookKind::FieldValidator => "field_validator",
+            PyHookKind::ModelValidator => "model_validator",
+            PyHookKind::FieldSerializer => "field_serializer",
+            PyHookKind::ModelSerializer => "model_serializer",
+            PyHookKind::ComputedField => "computed_field",
+        };
+        Value::Object(vec![
+            ("kind", Value::String(kind.to_string())),
+            (
+                "fields",
+                Value::Array(hook.fields.into_iter().map(Value::String).collect()),
+            ),
+        ])
+    });
+    Value::Object(vec![
+        ("name", Value::String(method.name.clone())),
+        ("access", Value::String(access.to_string())),
+        ("kind", Value::String(kind.to_string())),
+        (
+            "decorators",
+            Value::Array(method.decorators.iter().map(expr).collect()),
+        ),
+        ("hook", hook.unwrap_or(Value::Null)),
+        ("params", Value::Array(params)),
+        ("returns", optional_expr(method.returns.as_ref())),
+        ("line", Value::Number(method.span.line)),
+    ])
+}
+
+fn expr(expr: &Expr) -> Value<'static> {
+    Value::String(expr.text.clone())
+}
+
+fn optional_expr(value: Option<&Expr>) -> Value<'static> {
+    value.map(expr).unwrap_or(Value::Null)
+}
+
+fn optional(value: Option<String>) -> Value<'static> {
+    value.map(Value::String).unwrap_or(Value::Null)
+}
+
+/// The subset of JSON needed for the document.
+enum Value<'a> {
+    Null,
+    Number(usize),
+    String(String),
+    Array(Vec<Value<'a>>),
+    /// Keys in insertion order, so that documents are stable.
+    Object(Vec<(&'a str, Value<'a>)>),
+}
+
+impl Value<'_> {
+    fn write(&self, f: &mut fmt::Formatter<'_>, depth: usize) -> fmt::Result {
+        let indent = "  ".repeat(depth + 1);
+        let outdent = "  ".repeat(depth);
+        match self {
+            Value::Null => write!(f, "null"),
+            Value::Number(number) => write!(f, "{}", number),
+            Value::String(string) => write!(f, "{}", quote(string)),
+            Value::Array(items) if items.is_empty() => write!(f, "[]"),
+            Value::Array(items) => {
+                writeln!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    write!(f, "{}", indent)?;
+                    item.write(f, depth + 1)?;
+                    writeln!(f, "{}", if i + 1 < items.len() { "," } else { "" })?;
+                }
+                write!(f, "{}]", outdent)
+            }
+            Value::Object(entries) if entries.is_empty() => write!(f, "{{}}"),
+            Value::Object(entries) => {
+                writeln!(f, "{{")?;
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    write!(f, "{}{}: ", indent, quote(key))?;
+                    value.write(f, depth + 1)?;
+                    writeln!(f, "{}", if i + 1 < entries.len() { "," } else { "" })?;
+                }
+                write!(f, "{}}}", outdent)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Value<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write(f, 0)
+    }
+}
+
+/// Quotes a JSON string, escaping quotes, backslashes and control characters.
+fn quote(text: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::scanner;
+
+    #[test]
+    fn test_to_json() {
+        let models = scanner::lex(
+            concat!(
+                "import pydantic as pd\n",
+                "class Item(pd.BaseModel):\n",
+                "    name: str = Field('a\"b', alias='n')\n",
+                "    def _check(self, *args) -> bool: ...\n",
+            )
+            .to_string(),
+        )
+        .unwrap();
+        let expected = r#"{
+  "version": 2,
+  "classes": [
+    {
+      "name": "Item",
+      "qualname": "Item",
+      "module": null,
+      "file": null,
+      "line": 2,
+      "kind": "model",
+      "decorators": [],
+      "parents": [
+        "pd.BaseModel"
+      ],
+      "bases": [
+        "pydantic.BaseModel"
+      ],
+      "fields": [
+        {
+          "name": "name",
+          "type": "str",
+          "default": "'a\"b'",
+          "default_factory": null,
+          "kind": "defaulted",
+          "metadata": {
+            "default": "'a\"b'",
+            "alias": "'n'"
+          },
+          "line": 3
+        }
+      ],
+      "members": [],
+      "methods": [
+        {
+          "name": "_check",
+          "access": "private",
+          "kind": "instance",
+          "decorators": [],
+          "hook": null,
+          "params": [
+            {
+              "name": "self",
+              "type": null,
+              "default": null
+            },
+            {
+              "name": "*args",
+              "type": null,
+              "default": null
+            }
+          ],
+          "returns": "bool",
+          "line": 4
+        }
+      ],
+      "config": {},
+      "classes": []
+    }
+  ]
+}"#;
+        assert_eq!(to_json(&models), expected);
+    }
+
+    #[test]
+    fn test_config() {
+        let models = scanner::lex(
+            concat!(
+                "class Item(pydantic.BaseModel, frozen=True, strict=True):\n",
+                "    model_config = ConfigDict(frozen=False)\n",
+            )
+            .to_string(),
+        )
+        .unwrap();
+        let json = to_json(&models);
+        assert_eq!(json.matches("\"frozen\"").count(), 1);
+        assert!(json.contains("\"frozen\": \"False\""));
+        assert!(json.contains("\"strict\": \"True\""));
+    }
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(quote("a\"b\\c\nd\u{1}"), r#""a\"b\\c\nd\u0001""#);
+    }
+}
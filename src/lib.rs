@@ -1,11 +1,186 @@
-use std::error::Error;
+use diagnostic::{Diagnostic, Severity};
+use scanner::UniqueVec;
+use std::{error::Error, io, path::PathBuf};
 
+pub mod ast;
 mod consts;
+pub mod diagnostic;
+pub mod dot;
+pub mod filter;
+pub mod json;
 pub mod mermaid;
+pub mod parser;
+pub mod plantuml;
+pub mod relations;
 pub mod scanner;
+pub mod tokenizer;
 
-pub fn transform(src: String) -> Result<String, Box<dyn Error>> {
-    let mut lines = vec![];
-    mermaid::ClassDiagram::make(scanner::lex(src)?, &mut lines)?;
-    Ok(lines.join("\r\n"))
+/// Options controlling what is drawn in a diagram.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RenderOptions {
+    /// Show the metadata of pydantic fields (alias, constraints, ...) next
+    /// to the field.
+    pub field_metadata: bool,
+    /// Show the validators and serializers pydantic applies to each field
+    /// next to the field.
+    pub validators: bool,
+    /// Draw a dependency from a class to every other scanned class named in
+    /// its method signatures.
+    pub method_dependencies: bool,
+    /// Group classes by the Python package they were scanned from, where the
+    /// output format supports it.
+    pub namespaces: bool,
+}
+
+/// An output format for a set of scanned classes.
+///
+/// The built-in formats are registered in `Registry::default()`; other
+/// crates can add their own with `Registry::register`.
+pub trait Renderer {
+    /// Conventional file extension of diagrams in this format.
+    fn extension(&self) -> &str;
+
+    /// Writes a diagram of `models` to `out`.
+    fn render(
+        &self,
+        models: &[scanner::PyClass],
+        options: &RenderOptions,
+        out: &mut dyn io::Write,
+    ) -> Result<(), Box<dyn Error>>;
+}
+
+/// Output formats by name, e.g., `mermaid`.
+pub struct Registry(Vec<(String, Box<dyn Renderer>)>);
+
+impl Registry {
+    /// Makes `renderer` available as `name`, replacing any format previously
+    /// registered under that name.
+    pub fn register(&mut self, name: &str, renderer: Box<dyn Renderer>) {
+        self.0.retain(|(other, _)| other != name);
+        self.0.push((name.to_string(), renderer));
+    }
+
+    /// Returns the name of the first format whose diagrams conventionally
+    /// have `extension`, e.g., `plantuml` for `puml`.
+    pub fn find_by_extension(&self, extension: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(_, renderer)| renderer.extension() == extension)
+            .map(|(name, _)| name.as_str())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn Renderer> {
+        self.0
+            .iter()
+            .find(|(other, _)| other == name)
+            .map(|(_, renderer)| renderer.as_ref())
+    }
+
+    /// Names of the registered formats, in the order they were registered.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().map(|(name, _)| name.as_str())
+    }
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        let mut registry = Registry(vec![]);
+        registry.register("mermaid", Box::new(mermaid::ClassDiagram));
+        registry.register("plantuml", Box::new(plantuml::ClassDiagram));
+        registry.register("dot", Box::new(dot::ClassDiagram));
+        registry.register("json", Box::new(json::Json));
+        registry
+    }
+}
+
+pub fn transform(
+    src: String,
+    renderer: &dyn Renderer,
+    options: &RenderOptions,
+) -> Result<String, Box<dyn Error>> {
+    let models = scanner::lex(src)?;
+    let mut out = vec![];
+    renderer.render(&models, options, &mut out)?;
+    Ok(String::from_utf8(out)?)
+}
+
+/// Scans Python source files, along with notes about the statements that
+/// were skipped. Classes are told apart by their module, so that classes of
+/// the same name in different files are all kept.
+pub fn scan_files(
+    files: &[PathBuf],
+) -> Result<(Vec<scanner::PyClass>, Vec<Diagnostic>), Box<dyn Error>> {
+    let mut models = vec![];
+    let mut diagnostics = vec![];
+    for file in files.iter() {
+        let (found, notes) = scanner::lex_file(file)?;
+        models.extend(found);
+        diagnostics.extend(notes);
+    }
+    models.remove_dups();
+    Ok((models, diagnostics))
+}
+
+/// Scans Python source files like `scan_files`, but skips what cannot be
+/// scanned, including files that cannot be read, and reports it instead.
+pub fn scan_files_lenient(files: &[PathBuf]) -> (Vec<scanner::PyClass>, Vec<Diagnostic>) {
+    let mut models = vec![];
+    let mut diagnostics = vec![];
+    for file in files.iter() {
+        match scanner::lex_file_lenient(file) {
+            Ok((found, problems)) => {
+                models.extend(found);
+                diagnostics.extend(problems);
+            }
+            Err(err) => diagnostics.push(Diagnostic::new(Severity::Warning, err.to_string())),
+        }
+    }
+    models.remove_dups();
+    (models, diagnostics)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct ClassNames;
+    impl Renderer for ClassNames {
+        fn extension(&self) -> &str {
+            "txt"
+        }
+
+        fn render(
+            &self,
+            models: &[scanner::PyClass],
+            _options: &RenderOptions,
+            out: &mut dyn io::Write,
+        ) -> Result<(), Box<dyn Error>> {
+            for model in models.iter() {
+                writeln!(out, "{}", model.name)?;
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_registry() {
+        let mut registry = Registry::default();
+        registry.register("names", Box::new(ClassNames));
+        assert_eq!(
+            registry.names().collect::<Vec<_>>(),
+            ["mermaid", "plantuml", "dot", "json", "names"]
+        );
+        let renderer = registry.get("names").unwrap();
+        let out = transform(
+            "class A: ...\nclass B: ...\n".to_string(),
+            renderer,
+            &RenderOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(out, "A\nB\n");
+        assert!(registry.get("svg").is_none());
+        assert_eq!(registry.find_by_extension("puml"), Some("plantuml"));
+        assert_eq!(registry.find_by_extension("txt"), Some("names"));
+        assert_eq!(registry.find_by_extension("svg"), None);
+    }
 }
@@ -1,18 +1,81 @@
-use std::{env, fs, path::Path, process};
+use std::{
+    env, fs,
+    io::{self, Write},
+    process,
+};
+mod cli;
+mod glob;
 mod utils;
 
+/// Exit code for invalid command line arguments.
+const USAGE_ERROR: i32 = 2;
+/// Exit code for sources that could not be read, scanned or rendered.
+const FAILURE: i32 = 1;
+
 fn main() {
-    let args: Vec<String> = env::args().collect();
-    if args.len() != 2 {
-        eprintln!("Usage: nereus <path to .py files>");
-        process::exit(-1);
-    }
-    let src = utils::read_files(Path::new(&args[1]), Option::None)
-        .expect("Failed to read source file(s).");
-    match nereus::transform(src) {
-        Ok(out) => fs::write("test.mmd", out).expect("Failed to write output to file."),
+    let registry = nereus::Registry::default();
+    let usage = cli::USAGE.replace(
+        "{formats}",
+        &registry.names().collect::<Vec<_>>().join(", "),
+    );
+    let args = match cli::parse(env::args().skip(1)) {
+        Ok(cli::Command::Run(args)) => args,
+        Ok(cli::Command::Help) => {
+            println!("{usage}");
+            return;
+        }
+        Ok(cli::Command::Version) => {
+            println!("nereus {}", env!("CARGO_PKG_VERSION"));
+            return;
+        }
         Err(err) => {
-            eprintln!("Failed to generate mermaid. Found error: {err}")
+            eprintln!("{err}\n\n{usage}");
+            process::exit(USAGE_ERROR);
         }
     };
+    let format = args.format(&registry);
+    let Some(renderer) = registry.get(format) else {
+        eprintln!("Unknown output format: {format}\n\n{usage}");
+        process::exit(USAGE_ERROR);
+    };
+
+    let files = utils::find_files(&args.path, &args.filter).unwrap_or_else(|err| {
+        eprintln!("Failed to read {}: {err}", args.path.display());
+        process::exit(FAILURE);
+    });
+    let (models, diagnostics) = if args.lenient {
+        nereus::scan_files_lenient(&files)
+    } else {
+        // Scan errors are reported as source snippets that need no prefix.
+        nereus::scan_files(&files).unwrap_or_else(|err| {
+            eprintln!("{err}");
+            process::exit(FAILURE);
+        })
+    };
+    for diagnostic in diagnostics.iter().filter(|d| d.severity <= args.report) {
+        eprintln!("{diagnostic}\n");
+    }
+    let models = args.classes.apply(&models).unwrap_or_else(|err| {
+        eprintln!("{err}");
+        process::exit(FAILURE);
+    });
+    let mut out = vec![];
+    if let Err(err) = renderer.render(&models, &args.options, &mut out) {
+        eprintln!("Failed to generate {format}. Found error: {err}");
+        process::exit(FAILURE);
+    }
+    let written = match &args.output {
+        cli::Output::Stdout => io::stdout()
+            .write_all(&out)
+            .and_then(|_| writeln!(io::stdout())),
+        cli::Output::File(path) => fs::write(path, out),
+    };
+    // A closed pipe, e.g., `nereus models | head`, is not an error.
+    match written {
+        Err(err) if err.kind() != io::ErrorKind::BrokenPipe => {
+            eprintln!("Failed to write output: {err}");
+            process::exit(FAILURE);
+        }
+        _ => {}
+    }
 }
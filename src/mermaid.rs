@@ -1,91 +1,503 @@
 use crate::{
     consts,
-    scanner::{PyClass, PyMethodAccess},
+    relations::{self, ClassIndex, Edge},
+    scanner::{
+        PyAttribute, PyClass, PyClassKind, PyFieldKind, PyHook, PyMethod, PyMethodModifier,
+        PyParam, SignatureStyle,
+    },
+    RenderOptions, Renderer,
+};
+use std::{error::Error, io};
+
+/// Mermaid puts parameter types first, e.g., `+scale(float factor) None`.
+const SIGNATURE: SignatureStyle = SignatureStyle {
+    type_first: true,
+    returns: " ",
 };
 
 pub struct ClassDiagram;
 impl ClassDiagram {
-    pub fn make(models: Vec<PyClass>, lines: &mut Vec<String>) -> Result<(), &str> {
-        let inherits = " <|-- ";
-        for model in models.iter() {
-            if lines.is_empty() {
-                lines.push("classDiagram".to_string());
-            }
-
-            // Define class as well as the fields and methods therein.
-            let class_name = format!("{}class {}{{", consts::INDENT, model.name);
-            lines.push(class_name);
-            for prop in model.props.iter() {
-                let line = if prop.type_.is_some() {
-                    format!(
-                        "{}{}+{} {}",
-                        consts::INDENT,
-                        consts::INDENT,
-                        prop.name,
-                        prop.type_.clone().unwrap()
-                    )
-                } else if prop.default.is_some() {
-                    format!(
-                        "{}{}+{} = {}",
-                        consts::INDENT,
-                        consts::INDENT,
-                        prop.name,
-                        prop.default.clone().unwrap()
-                    )
-                } else {
-                    format!("{}{}+{}", consts::INDENT, consts::INDENT, prop.name)
+    pub fn make(
+        models: &[PyClass],
+        options: &RenderOptions,
+        lines: &mut Vec<String>,
+    ) -> Result<(), &'static str> {
+        let index = ClassIndex::new(models);
+        if !models.is_empty() && lines.is_empty() {
+            lines.push("classDiagram".to_string());
+        }
+        if options.namespaces {
+            // Namespaces may only hold class definitions, so all edges are
+            // drawn after them.
+            for (package, group) in relations::group_by(models, PyClass::package) {
+                let indent = match &package {
+                    Some(package) => {
+                        lines.push(format!("{}namespace {} {{", consts::INDENT, package));
+                        consts::INDENT.repeat(2)
+                    }
+                    None => consts::INDENT.to_string(),
                 };
-                lines.push(line);
+                for model in group {
+                    Self::make_class(model, &index, options, &indent, lines);
+                }
+                if package.is_some() {
+                    lines.push(format!("{}}}", consts::INDENT));
+                }
+            }
+            for model in models.iter() {
+                Self::make_edges(model, &index, lines);
             }
+        } else {
+            for model in models.iter() {
+                Self::make_class(model, &index, options, consts::INDENT, lines);
+                Self::make_edges(model, &index, lines);
+            }
+        }
+        for relation in relations::relations(models, options).iter() {
+            lines.push(format!("{}{}", consts::INDENT, relation.to_uml(class_id)));
+        }
+        Ok(())
+    }
 
-            for method in &model.methods {
-                let access_modifier: &str;
-                if method.is_dunder() {
-                    continue;
+    /// Draws the inheritance of a class and of the classes nested in it. A
+    /// nested class is linked to its outer class with a dashed line, as a
+    /// solid `*--` would read as a composition.
+    fn make_edges(model: &PyClass, index: &ClassIndex, lines: &mut Vec<String>) {
+        for edge in relations::edges(model, index) {
+            let line = match edge {
+                Edge::Inherits { child, parent } => {
+                    format!("`{}` <|-- {}", parent, class_id(&child))
                 }
-                match method.access {
-                    PyMethodAccess::Public => access_modifier = "+",
-                    PyMethodAccess::Private => access_modifier = "-",
+                Edge::Nests { outer, inner } => {
+                    format!("{} .. {} : nested", class_id(&outer), class_id(&inner))
                 }
+            };
+            lines.push(format!("{}{}", consts::INDENT, line));
+        }
+    }
 
-                let mut method_str = format!(
-                    "{}{}{}{}(",
-                    consts::INDENT,
-                    consts::INDENT,
-                    access_modifier,
-                    method.name,
-                );
-                let mut args: Vec<String> = vec![];
-                for param in method.params.clone() {
-                    let type_ = param.type_.unwrap_or_default();
-                    if type_.is_empty() {
-                        args.push(param.name);
-                    } else {
-                        args.push(format!("{} {}", type_, param.name));
-                    }
+    /// Defines a class and the classes nested in it at `indent`.
+    fn make_class(
+        model: &PyClass,
+        index: &ClassIndex,
+        options: &RenderOptions,
+        indent: &str,
+        lines: &mut Vec<String>,
+    ) {
+        let model_id = class_id(&index.id(model));
+        // Define class as well as the fields and methods therein.
+        let class_name = format!("{}class {}{{", indent, model_id);
+        lines.push(class_name);
+        // Pydantic models are the norm, so only other kinds are annotated.
+        let kind = index.kind(model);
+        if let Some(stereotype) = kind.stereotype().filter(|_| kind != PyClassKind::Model) {
+            lines.push(format!("{}{}<<{}>>", indent, consts::INDENT, stereotype));
+        }
+        for member in model.members.iter() {
+            lines.push(format!("{}{}{}", indent, consts::INDENT, member));
+        }
+        for attribute in model.attributes() {
+            let mut line = format!(
+                "{}{}{}{}",
+                indent,
+                consts::INDENT,
+                attribute.access().symbol(),
+                attribute.name()
+            );
+            if matches!(attribute, PyAttribute::Field(prop) if prop.kind() == PyFieldKind::Optional)
+            {
+                line.push('?');
+            }
+            if let Some(type_) = attribute.type_() {
+                line.push_str(&format!(" {}", type_));
+            }
+            if let PyAttribute::Field(prop) = attribute {
+                if let Some(default) = prop.default_text() {
+                    line.push_str(&format!(" = {}", default));
                 }
-                if args.len() > 0 {
-                    let args_str = args.join(", ");
-                    method_str.push_str(args_str.as_str());
+                if options.field_metadata {
+                    line.push_str(&field_metadata(prop));
                 }
-                method_str.push_str(")");
-                if let Option::Some(return_type) = &method.returns {
-                    method_str.push_str(format!(" {}", return_type).as_str());
+                if options.validators {
+                    line.push_str(&field_hooks(model, &prop.name));
                 }
-                lines.push(method_str);
-            }
-            lines.push(format!("{}}}", consts::INDENT));
-
-            for parent in model.parents.iter() {
-                lines.push(format!(
-                    "{}`{}`{}{}",
-                    consts::INDENT,
-                    parent,
-                    inherits,
-                    model.name
-                ));
             }
+            lines.push(line);
         }
+
+        for method in model.drawn_methods() {
+            // Mermaid's classifiers.
+            let classifier = match method.modifier() {
+                Some(PyMethodModifier::Static) => "$",
+                Some(PyMethodModifier::Abstract) => "*",
+                None => "",
+            };
+            lines.push(format!(
+                "{}{}{}{}{}",
+                indent,
+                consts::INDENT,
+                method.access.symbol(),
+                method.signature(&SIGNATURE),
+                classifier
+            ));
+        }
+        lines.push(format!("{}}}", indent));
+
+        // Nested classes are drawn separately and linked to their outer class.
+        for child in model.children.iter() {
+            Self::make_class(child, index, options, indent, lines);
+        }
+    }
+}
+
+impl Renderer for ClassDiagram {
+    fn extension(&self) -> &str {
+        "mmd"
+    }
+
+    fn render(
+        &self,
+        models: &[PyClass],
+        options: &RenderOptions,
+        out: &mut dyn io::Write,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut lines = vec![];
+        Self::make(models, options, &mut lines)?;
+        write!(out, "{}", lines.join("\r\n"))?;
         Ok(())
     }
 }
+
+/// Formats the `Field(...)` metadata of a field, e.g., ` [alias='ID', gt=0]`.
+fn field_metadata(prop: &PyParam) -> String {
+    let entries = prop
+        .field
+        .iter()
+        .flat_map(|field| field.entries())
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect::<Vec<String>>();
+    if entries.is_empty() {
+        String::new()
+    } else {
+        format!(" [{}]", entries.join(", "))
+    }
+}
+
+/// Names the methods validating and serializing a field, e.g.,
+/// ` [validated by check_id, serialized by dump_id]`.
+fn field_hooks(model: &PyClass, name: &str) -> String {
+    let (serializers, validators): (Vec<_>, Vec<_>) = model
+        .hooks_of(name)
+        .into_iter()
+        .partition(|(_, hook)| hook.is_serializer());
+    let names = |hooks: Vec<(&PyMethod, PyHook)>| {
+        hooks
+            .into_iter()
+            .map(|(method, _)| method.name.clone())
+            .collect::<Vec<String>>()
+            .join(", ")
+    };
+    let mut parts = vec![];
+    if !validators.is_empty() {
+        parts.push(format!("validated by {}", names(validators)));
+    }
+    if !serializers.is_empty() {
+        parts.push(format!("serialized by {}", names(serializers)));
+    }
+    if parts.is_empty() {
+        String::new()
+    } else {
+        format!(" [{}]", parts.join(", "))
+    }
+}
+
+/// Quotes a class name in backticks unless it is a plain identifier, e.g.,
+/// for qualified names of nested classes.
+fn class_id(name: &str) -> String {
+    if name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        name.to_string()
+    } else {
+        format!("`{}`", name)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::scanner;
+
+    fn render(source: &str, options: &RenderOptions) -> Vec<String> {
+        let mut lines = vec![];
+        let models = scanner::lex(source.to_string()).unwrap();
+        ClassDiagram::make(&models, options, &mut lines).unwrap();
+        lines
+            .into_iter()
+            .map(|line| line.trim().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_field_metadata() {
+        let source = concat!(
+            "class Model(pydantic.BaseModel):\n",
+            "    id: int = Field(default=1, alias='ID', gt=0)\n",
+        );
+        let lines = render(source, &RenderOptions::default());
+        assert_eq!(lines[2], "+id int = 1");
+
+        let options = RenderOptions {
+            field_metadata: true,
+            ..Default::default()
+        };
+        let lines = render(source, &options);
+        assert_eq!(lines[2], "+id int = 1 [default=1, alias='ID', gt=0]");
+    }
+
+    #[test]
+    fn test_default_factories() {
+        let lines = render(
+            concat!(
+                "class Model(pydantic.BaseModel):\n",
+                "    a: list[int] = Field(default_factory=list)\n",
+                "    b: Config = Field(default_factory=config.Config)\n",
+                "    c: int = Field(default_factory=lambda: 1)\n",
+            ),
+            &RenderOptions::default(),
+        );
+        assert_eq!(
+            lines[2..5],
+            [
+                "+a list[int] = list()",
+                "+b Config = config.Config()",
+                "+c int = <factory: lambda: 1>",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_method_decorators() {
+        let lines = render(
+            concat!(
+                "class Shape(pydantic.BaseModel):\n",
+                "    sides: int\n",
+                "    @property\n",
+                "    def _key(self) -> str: ...\n",
+                "    @_key.setter\n",
+                "    def _key(self, value: str) -> None: ...\n",
+                "    @staticmethod\n",
+                "    def unit(scale: float) -> 'Shape': ...\n",
+                "    @classmethod\n",
+                "    def create(cls, sides: int) -> 'Shape': ...\n",
+                "    @abstractmethod\n",
+                "    def area(self) -> float: ...\n",
+            ),
+            &RenderOptions::default(),
+        );
+        assert_eq!(
+            lines[1..8],
+            [
+                "class Shape{",
+                "+sides int",
+                "-_key str",
+                "+unit(float scale) 'Shape'$",
+                "+create(int sides) 'Shape'",
+                "+area() float*",
+                "}",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validators() {
+        let source = concat!(
+            "class User(pydantic.BaseModel):\n",
+            "    id: int\n",
+            "    name: str\n",
+            "    @field_validator('id', 'name')\n",
+            "    def strip(cls, value): ...\n",
+            "    @field_serializer('name')\n",
+            "    def dump_name(self, value): ...\n",
+            "    @computed_field\n",
+            "    @property\n",
+            "    def label(self) -> str: ...\n",
+        );
+        let lines = render(source, &RenderOptions::default());
+        assert_eq!(lines[2..5], ["+id int", "+name str", "+label str"]);
+
+        let options = RenderOptions {
+            validators: true,
+            ..Default::default()
+        };
+        let lines = render(source, &options);
+        assert_eq!(
+            lines[2..5],
+            [
+                "+id int [validated by strip]",
+                "+name str [validated by strip, serialized by dump_name]",
+                "+label str",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_enumeration() {
+        let lines = render(
+            concat!(
+                "class Color(IntEnum):\n",
+                "    RED = 1\n",
+                "    GREEN = auto()\n",
+                "class Item(pydantic.BaseModel):\n",
+                "    color: Color\n",
+            ),
+            &RenderOptions::default(),
+        );
+        assert_eq!(
+            lines[1..6],
+            [
+                "class Color{",
+                "<<enumeration>>",
+                "RED = 1",
+                "GREEN = auto()",
+                "}"
+            ]
+        );
+        assert_eq!(lines.last().unwrap(), "Item --> \"1\" Color : color");
+    }
+
+    #[test]
+    fn test_stereotypes() {
+        let lines = render(
+            concat!(
+                "@dataclass\n",
+                "class Point:\n",
+                "    x: int\n",
+                "class Movie(TypedDict):\n",
+                "    title: str\n",
+                "class Model(pydantic.BaseModel): ...\n",
+            ),
+            &RenderOptions::default(),
+        );
+        assert_eq!(
+            lines[1..10],
+            [
+                "class Point{",
+                "<<dataclass>>",
+                "+x int",
+                "}",
+                "class Movie{",
+                "<<TypedDict>>",
+                "+title str",
+                "}",
+                "`TypedDict` <|-- Movie",
+            ]
+        );
+        assert_eq!(lines[10..12], ["class Model{", "}"]);
+    }
+
+    #[test]
+    fn test_field_kinds() {
+        let lines = render(
+            concat!(
+                "class Model(pydantic.BaseModel):\n",
+                "    required: str\n",
+                "    nickname: str | None\n",
+                "    name: str = \"hello\"\n",
+                "    tags: list[str] = Field(default_factory=list)\n",
+                "    untyped = 1\n",
+            ),
+            &RenderOptions::default(),
+        );
+        assert_eq!(
+            lines[2..7],
+            [
+                "+required str",
+                "+nickname? str | None",
+                "+name str = \"hello\"",
+                "+tags list[str] = list()",
+                "+untyped = 1",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_field_relations() {
+        let lines = render(
+            concat!(
+                "class Item(pydantic.BaseModel):\n",
+                "    id: int\n",
+                "class Order(pydantic.BaseModel):\n",
+                "    items: list[Item]\n",
+                "    gift: Item | None\n",
+            ),
+            &RenderOptions::default(),
+        );
+        assert_eq!(
+            lines[lines.len() - 2..],
+            [
+                "Order *-- \"*\" Item : items",
+                "Order *-- \"0..1\" Item : gift"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_method_dependencies() {
+        let source = concat!(
+            "class Item:\n",
+            "    pass\n",
+            "class Cart:\n",
+            "    def add(self, item: Item) -> None: ...\n",
+            "    def pop(self) -> Item: ...\n",
+        );
+        let lines = render(source, &RenderOptions::default());
+        assert!(!lines.iter().any(|line| line.contains("..>")));
+
+        let options = RenderOptions {
+            method_dependencies: true,
+            ..Default::default()
+        };
+        let lines = render(source, &options);
+        let edges = lines.iter().filter(|line| line.contains("..>"));
+        assert_eq!(edges.collect::<Vec<_>>(), ["Cart ..> Item"]);
+    }
+
+    #[test]
+    fn test_namespaces() {
+        let mut models =
+            scanner::lex("class A: ...\nclass B(A): ...\nclass C: ...\n".to_string()).unwrap();
+        models[0].module = Some("shop.orders.models".to_string());
+        models[1].module = Some("shop.orders.models".to_string());
+        models[2].module = Some("main".to_string());
+        let options = RenderOptions {
+            namespaces: true,
+            ..Default::default()
+        };
+        let mut lines = vec![];
+        ClassDiagram::make(&models, &options, &mut lines).unwrap();
+        assert_eq!(
+            lines,
+            [
+                "classDiagram",
+                "    namespace shop.orders {",
+                "        class A{",
+                "        }",
+                "        class B{",
+                "        }",
+                "    }",
+                "    class C{",
+                "    }",
+                "    `A` <|-- B",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_nested_class() {
+        let lines = render(
+            "class Outer:\n    class Inner:\n        pass\n",
+            &RenderOptions::default(),
+        );
+        assert!(lines.contains(&"class `Outer.Inner`{".to_string()));
+        assert!(lines.contains(&"Outer .. `Outer.Inner` : nested".to_string()));
+    }
+}
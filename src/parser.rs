@@ -0,0 +1,1119 @@
+use crate::{
+    ast::{
+        Alias, AnnAssign, Assign, ClassDef, Expr, ExprKind, FunctionDef, Import, ImportFrom,
+        Keyword, Module, Param, ParamKind, Span, Stmt,
+    },
+    diagnostic::ScanError,
+    tokenizer::{self, Token, TokenKind},
+};
+
+/// Keywords opening a compound statement, whose header ends in a colon.
+const COMPOUND_KEYWORDS: [&str; 13] = [
+    "class", "def", "async", "if", "elif", "else", "for", "while", "with", "try", "except",
+    "finally", "match",
+];
+
+/// Keywords opening a simple statement that is not modelled by the tree.
+const STATEMENT_KEYWORDS: [&str; 12] = [
+    "import", "from", "return", "raise", "del", "global", "nonlocal", "assert", "break",
+    "continue", "type", "lambda",
+];
+
+/// Keywords only when not used as a name, e.g., `type` is a field in
+/// `type: str` but starts an alias in `type Id = int`.
+const SOFT_KEYWORDS: [&str; 2] = ["match", "type"];
+
+/// Keywords that can never start or continue an expression we model.
+const RESERVED: [&str; 20] = [
+    "lambda", "yield", "await", "if", "else", "for", "while", "with", "try", "except", "finally",
+    "def", "class", "return", "import", "from", "pass", "del", "global", "nonlocal",
+];
+
+const AUGMENTED_ASSIGNMENTS: [&str; 13] = [
+    "+=", "-=", "*=", "/=", "//=", "%=", "@=", "&=", "|=", "^=", ">>=", "<<=", "**=",
+];
+
+/// Binary operators and their precedence, from loosest to tightest binding.
+const BINARY_OPERATORS: [(&str, u8); 25] = [
+    ("or", 1),
+    ("and", 2),
+    ("in", 4),
+    ("not in", 4),
+    ("is", 4),
+    ("is not", 4),
+    ("==", 4),
+    ("!=", 4),
+    ("<", 4),
+    (">", 4),
+    ("<=", 4),
+    (">=", 4),
+    ("|", 5),
+    ("^", 6),
+    ("&", 7),
+    ("<<", 8),
+    (">>", 8),
+    ("+", 9),
+    ("-", 9),
+    ("*", 10),
+    ("/", 10),
+    ("//", 10),
+    ("%", 10),
+    ("@", 10),
+    ("**", 12),
+];
+
+struct Placeholder;
+impl Placeholder {
+    const PASS: &str = "pass";
+}
+
+/// A logical line of Python source, i.e., the tokens of a single statement
+/// or of a compound statement header.
+#[derive(Clone, Copy)]
+struct Line<'a> {
+    /// Number of blocks enclosing the line.
+    indent: usize,
+    tokens: &'a [Token],
+}
+
+trait PyLine {
+    fn is_decorator(&self) -> bool;
+    fn is_method(&self) -> bool;
+    fn is_class(&self) -> bool;
+    fn is_compound(&self) -> bool;
+    fn get_declr_name(&self) -> Result<String, ScanError>;
+}
+
+impl PyLine for Line<'_> {
+    fn is_decorator(&self) -> bool {
+        self.tokens[0].is_op("@")
+    }
+
+    fn is_method(&self) -> bool {
+        let first = &self.tokens[0];
+        first.is_name("def")
+            || (first.is_name("async") && self.tokens.get(1).is_some_and(|t| t.is_name("def")))
+    }
+
+    fn is_class(&self) -> bool {
+        self.tokens[0].is_name("class")
+    }
+
+    fn is_compound(&self) -> bool {
+        starts_with_keyword(self.tokens, &COMPOUND_KEYWORDS)
+    }
+
+    fn get_declr_name(&self) -> Result<String, ScanError> {
+        let keyword = self
+            .tokens
+            .iter()
+            .position(|t| t.is_name("def") || t.is_name("class"));
+        match keyword.and_then(|k| self.tokens.get(k + 1)) {
+            Some(name) if name.kind == TokenKind::Name => Ok(name.text.clone()),
+            _ => Err(ScanError::InvalidDeclaration {
+                span: span_of(self.tokens),
+            }),
+        }
+    }
+}
+
+/// Groups a token stream into logical lines, tracking the depth of the
+/// block each line belongs to.
+fn logical_lines(tokens: &[Token]) -> Vec<Line<'_>> {
+    let mut lines = vec![];
+    let mut indent = 0;
+    let mut start = 0;
+    for (i, token) in tokens.iter().enumerate() {
+        match token.kind {
+            TokenKind::Indent => indent += 1,
+            TokenKind::Dedent => indent -= 1,
+            TokenKind::Newline | TokenKind::EndMarker => {
+                if start < i {
+                    lines.push(Line {
+                        indent,
+                        tokens: &tokens[start..i],
+                    });
+                }
+            }
+            _ => continue,
+        }
+        start = i + 1;
+    }
+    lines
+}
+
+/// Splits `tokens` on every occurrence of the operator `sep` that is not
+/// nested inside brackets.
+fn split_top_level<'a>(tokens: &'a [Token], sep: &str) -> Vec<&'a [Token]> {
+    let mut parts = vec![];
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, token) in tokens.iter().enumerate() {
+        if token.kind != TokenKind::Op {
+            continue;
+        }
+        match token.text.as_str() {
+            "(" | "[" | "{" => depth += 1,
+            ")" | "]" | "}" => depth -= 1,
+            _ if depth == 0 && token.text == sep => {
+                parts.push(&tokens[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&tokens[start..]);
+    parts
+}
+
+/// Splits `tokens` at the first top-level occurrence of the operator `sep`.
+fn split_once_top_level<'a>(tokens: &'a [Token], sep: &str) -> (&'a [Token], Option<&'a [Token]>) {
+    let parts = split_top_level(tokens, sep);
+    if parts.len() == 1 {
+        (tokens, None)
+    } else {
+        let head = parts[0];
+        (head, Some(&tokens[head.len() + 1..]))
+    }
+}
+
+/// Splits a comma-separated list, dropping the empty item left by a
+/// trailing comma.
+fn split_list(tokens: &[Token]) -> Vec<&[Token]> {
+    split_top_level(tokens, ",")
+        .into_iter()
+        .filter(|part| !part.is_empty())
+        .collect()
+}
+
+fn contains_top_level(tokens: &[Token], pred: impl Fn(&Token) -> bool) -> bool {
+    let mut depth = 0;
+    for token in tokens {
+        if token.kind == TokenKind::Op {
+            match token.text.as_str() {
+                "(" | "[" | "{" => depth += 1,
+                ")" | "]" | "}" => depth -= 1,
+                _ => {}
+            }
+        }
+        if depth == 0 && pred(token) {
+            return true;
+        }
+    }
+    false
+}
+
+/// Returns the tokens enclosed by the bracket opened at `tokens[open]` along
+/// with the index of the matching closing bracket.
+fn enclosed(tokens: &[Token], open: usize) -> Result<(&[Token], usize), ScanError> {
+    let mut depth = 0;
+    for (i, token) in tokens.iter().enumerate().skip(open) {
+        if token.kind != TokenKind::Op {
+            continue;
+        }
+        match token.text.as_str() {
+            "(" | "[" | "{" => depth += 1,
+            ")" | "]" | "}" => {
+                depth -= 1;
+                if depth == 0 {
+                    return Ok((&tokens[open + 1..i], i));
+                }
+            }
+            _ => {}
+        }
+    }
+    Err(ScanError::UnterminatedBracket {
+        open: tokens[open].text.chars().next().unwrap_or('('),
+        span: tokens[open].span,
+    })
+}
+
+/// Skips the PEP 695 type parameters starting at `at`, if any, e.g.,
+/// `[T: int]` in `class A[T: int](Base):`, returning the index after them.
+fn skip_type_params(tokens: &[Token], at: usize) -> Result<usize, ScanError> {
+    if tokens.get(at).is_some_and(|t| t.is_op("[")) {
+        Ok(enclosed(tokens, at)?.1 + 1)
+    } else {
+        Ok(at)
+    }
+}
+
+fn span_of(tokens: &[Token]) -> Span {
+    match (tokens.first(), tokens.last()) {
+        (Some(first), Some(last)) => first.span.to(last.span),
+        _ => Span::default(),
+    }
+}
+
+/// Parses Python source into a syntax tree.
+pub fn parse(source: &str) -> Result<Module, ScanError> {
+    let tokens = tokenizer::tokenize(source)?;
+    let mut parser = Parser {
+        lines: logical_lines(&tokens),
+        pos: 0,
+        errors: None,
+    };
+    Ok(Module {
+        body: parser.block(None)?,
+    })
+}
+
+/// Parses Python source into a syntax tree, skipping what cannot be parsed
+/// instead of failing.
+///
+/// Lines the tokenizer rejects are blanked out and the source tokenized
+/// again; class and function definitions with a malformed header are
+/// dropped along with their body. Every construct skipped is reported by
+/// the error that caused it, in the order they appear in the source.
+pub fn parse_lenient(source: &str) -> (Module, Vec<ScanError>) {
+    let mut errors = vec![];
+    let mut source = source.to_string();
+    let tokens = loop {
+        match tokenizer::tokenize(&source) {
+            Ok(tokens) => break tokens,
+            Err(err) => {
+                let blanked = blank_lines(&source, err.span());
+                errors.push(err);
+                // Blanking always removes the offending character, but make
+                // sure never to loop on the same error.
+                if blanked == source {
+                    break vec![];
+                }
+                source = blanked;
+            }
+        }
+    };
+    let mut parser = Parser {
+        lines: logical_lines(&tokens),
+        pos: 0,
+        errors: Some(&mut errors),
+    };
+    // Errors are recorded rather than returned in lenient mode.
+    let body = parser.block(None).unwrap_or_default();
+    errors.sort_by_key(|err| err.span().start);
+    (Module { body }, errors)
+}
+
+/// Replaces the lines touched by `span` with spaces, keeping the offsets of
+/// the rest of the source intact.
+fn blank_lines(source: &str, span: Span) -> String {
+    let boundary = |mut offset: usize| {
+        while !source.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        offset
+    };
+    let start = boundary(span.start.min(source.len()));
+    let last = boundary(span.end.saturating_sub(1).clamp(start, source.len()));
+    let start = source[..start].rfind('\n').map_or(0, |i| i + 1);
+    let end = source[last..].find('\n').map_or(source.len(), |i| last + i);
+    // Each character is blanked byte for byte, so that multi-byte ones keep
+    // their width.
+    let blank = source[start..end]
+        .chars()
+        .map(|c| match c {
+            '\n' | '\r' => c.to_string(),
+            _ => " ".repeat(c.len_utf8()),
+        })
+        .collect::<String>();
+    format!("{}{}{}", &source[..start], blank, &source[end..])
+}
+
+struct Parser<'a, 'e> {
+    lines: Vec<Line<'a>>,
+    pos: usize,
+    /// Where to record the errors of malformed definitions, which are then
+    /// skipped instead of failing the parse.
+    errors: Option<&'e mut Vec<ScanError>>,
+}
+
+impl Parser<'_, '_> {
+    /// Parses the statements of a block, i.e., the lines following a
+    /// compound statement header (or the whole module) that are indented
+    /// deeper than the header.
+    fn block(&mut self, header_indent: Option<usize>) -> Result<Vec<Stmt>, ScanError> {
+        let mut body = vec![];
+        let mut decorators: Vec<Expr> = vec![];
+        let indent = match (header_indent, self.lines.get(self.pos)) {
+            (None, _) => 0,
+            (Some(header), Some(line)) if line.indent > header => line.indent,
+            _ => return Ok(body),
+        };
+
+        while let Some(&line) = self.lines.get(self.pos) {
+            if line.indent < indent {
+                break;
+            }
+            self.pos += 1;
+            // Lines indented deeper than the block without a header to own
+            // them are not valid Python; skip them.
+            if line.indent > indent {
+                continue;
+            }
+
+            if line.is_decorator() {
+                decorators.push(expr(&line.tokens[1..]));
+            } else if line.is_class() {
+                let class = self.class_def(line, std::mem::take(&mut decorators));
+                if let Some(class) = self.recover(class, line)? {
+                    body.push(Stmt::ClassDef(class));
+                }
+            } else if line.is_method() {
+                let func = self.function_def(line, std::mem::take(&mut decorators));
+                if let Some(func) = self.recover(func, line)? {
+                    body.push(Stmt::FunctionDef(func));
+                }
+            } else if line.is_compound() {
+                self.skip_block(line.indent);
+                body.push(Stmt::Other(self.span_from(line)));
+            } else {
+                body.extend(simple_statements(line.tokens));
+            }
+        }
+        Ok(body)
+    }
+
+    /// Passes on the result of parsing the definition headed by `line`, or,
+    /// when recording errors, records its error and skips the rest of it.
+    fn recover<T>(
+        &mut self,
+        result: Result<T, ScanError>,
+        line: Line,
+    ) -> Result<Option<T>, ScanError> {
+        match (result, &mut self.errors) {
+            (Ok(def), _) => Ok(Some(def)),
+            (Err(err), Some(errors)) => {
+                errors.push(err);
+                self.skip_block(line.indent);
+                Ok(None)
+            }
+            (Err(err), None) => Err(err),
+        }
+    }
+
+    fn skip_block(&mut self, header_indent: usize) {
+        while self
+            .lines
+            .get(self.pos)
+            .is_some_and(|l| l.indent > header_indent)
+        {
+            self.pos += 1;
+        }
+    }
+
+    /// Returns the span from the start of `header` to the end of the last
+    /// line consumed.
+    fn span_from(&self, header: Line) -> Span {
+        let last = self.lines[self.pos - 1].tokens.last().unwrap();
+        header.tokens[0].span.to(last.span)
+    }
+
+    fn class_def(&mut self, line: Line, decorators: Vec<Expr>) -> Result<ClassDef, ScanError> {
+        let name = line.get_declr_name()?;
+        let mut bases = vec![];
+        let mut keywords = vec![];
+
+        // Scan the base list, including any keyword arguments.
+        let mut colon = skip_type_params(line.tokens, 2)?;
+        if line.tokens.get(colon).is_some_and(|t| t.is_op("(")) {
+            let (args, close) = enclosed(line.tokens, colon)?;
+            let (args, kwargs) = call_args(args);
+            bases = args;
+            keywords = kwargs;
+            colon = close + 1;
+        }
+        if !line.tokens.get(colon).is_some_and(|t| t.is_op(":")) {
+            return Err(ScanError::MissingColon {
+                span: span_of(line.tokens),
+            });
+        }
+
+        let inline = &line.tokens[colon + 1..];
+        let body = if inline.is_empty() {
+            self.block(Some(line.indent))?
+        } else {
+            simple_statements(inline)
+        };
+
+        Ok(ClassDef {
+            name,
+            decorators,
+            bases,
+            keywords,
+            body,
+            span: self.span_from(line),
+        })
+    }
+
+    fn function_def(
+        &mut self,
+        line: Line,
+        decorators: Vec<Expr>,
+    ) -> Result<FunctionDef, ScanError> {
+        let name = line.get_declr_name()?;
+        let keyword = line.tokens.iter().position(|t| t.is_name("def"));
+        let open = skip_type_params(line.tokens, keyword.map_or(0, |k| k + 2))?;
+        if !line.tokens.get(open).is_some_and(|t| t.is_op("(")) {
+            return Err(ScanError::MissingParameters {
+                name,
+                span: span_of(line.tokens),
+            });
+        }
+        let (param_tokens, close) = enclosed(line.tokens, open)?;
+        let params = split_list(param_tokens).into_iter().map(param).collect();
+
+        // The return annotation sits between the arrow (->) and the
+        // terminating token (:).
+        let rest = &line.tokens[close + 1..];
+        let returns = match rest.first() {
+            Some(t) if t.is_op("->") => {
+                let (annotation, colon) = split_once_top_level(&rest[1..], ":");
+                if colon.is_none() || annotation.is_empty() {
+                    return Err(ScanError::MissingColon {
+                        span: span_of(line.tokens),
+                    });
+                }
+                Some(expr(annotation))
+            }
+            Some(t) if t.is_op(":") => None,
+            _ => {
+                return Err(ScanError::MissingColon {
+                    span: span_of(line.tokens),
+                })
+            }
+        };
+
+        self.skip_block(line.indent);
+        Ok(FunctionDef {
+            name,
+            is_async: line.tokens[0].is_name("async"),
+            decorators,
+            params,
+            returns,
+            span: self.span_from(line),
+        })
+    }
+}
+
+fn param(tokens: &[Token]) -> Param {
+    let span = span_of(tokens);
+    let (kind, tokens) = match tokens[0].text.as_str() {
+        "/" if tokens.len() == 1 => (ParamKind::PositionalOnlyMarker, tokens),
+        "*" if tokens.len() == 1 => (ParamKind::KeywordOnlyMarker, tokens),
+        "*" => (ParamKind::VarPositional, &tokens[1..]),
+        "**" => (ParamKind::VarKeyword, &tokens[1..]),
+        _ => (ParamKind::Regular, tokens),
+    };
+    let (name_and_type, default) = split_once_top_level(tokens, "=");
+    let (name, annotation) = split_once_top_level(name_and_type, ":");
+    Param {
+        name: tokenizer::untokenize(name),
+        kind,
+        annotation: annotation.filter(|a| !a.is_empty()).map(expr),
+        default: default.filter(|d| !d.is_empty()).map(expr),
+        span,
+    }
+}
+
+/// Returns whether the statement starts with one of `keywords`, used as a
+/// keyword rather than as a name being annotated or assigned.
+fn starts_with_keyword(tokens: &[Token], keywords: &[&str]) -> bool {
+    let first = &tokens[0];
+    keywords.iter().any(|k| first.is_name(k))
+        && !(SOFT_KEYWORDS.iter().any(|k| first.is_name(k))
+            && tokens.get(1).is_some_and(|t| t.is_op(":") || t.is_op("=")))
+}
+
+/// Parses a line of one or more simple statements separated by semicolons.
+fn simple_statements(tokens: &[Token]) -> Vec<Stmt> {
+    split_top_level(tokens, ";")
+        .into_iter()
+        .filter(|stmt| !stmt.is_empty())
+        .map(simple_statement)
+        .collect()
+}
+
+fn simple_statement(tokens: &[Token]) -> Stmt {
+    let span = span_of(tokens);
+    let first = &tokens[0];
+    if first.is_name(Placeholder::PASS) && tokens.len() == 1 {
+        return Stmt::Pass(span);
+    }
+    if first.is_name("import") || first.is_name("from") {
+        return import(tokens).unwrap_or(Stmt::Other(span));
+    }
+    if starts_with_keyword(tokens, &STATEMENT_KEYWORDS)
+        || contains_top_level(tokens, |t| {
+            t.kind == TokenKind::Op && AUGMENTED_ASSIGNMENTS.contains(&t.text.as_str())
+        })
+    {
+        return Stmt::Other(span);
+    }
+
+    let mut parts = split_top_level(tokens, "=");
+    if parts.iter().any(|part| part.is_empty()) {
+        return Stmt::Other(span);
+    }
+    let value = parts.pop().filter(|_| !parts.is_empty());
+    let (target, annotation) = split_once_top_level(parts.first().unwrap_or(&tokens), ":");
+    match (annotation, value) {
+        (Some(annotation), value) if parts.len() <= 1 && !annotation.is_empty() => {
+            Stmt::AnnAssign(AnnAssign {
+                target: expr(target),
+                annotation: expr(annotation),
+                value: value.map(expr),
+                span,
+            })
+        }
+        (None, Some(value)) => Stmt::Assign(Assign {
+            targets: parts.into_iter().map(expr).collect(),
+            value: expr(value),
+            span,
+        }),
+        (None, None) => Stmt::Expr(expr(tokens)),
+        _ => Stmt::Other(span),
+    }
+}
+
+/// Parses an `import` or `from ... import` statement, returning `None` if it
+/// is malformed.
+fn import(tokens: &[Token]) -> Option<Stmt> {
+    let span = span_of(tokens);
+    if tokens[0].is_name("import") {
+        return Some(Stmt::Import(Import {
+            names: aliases(&tokens[1..])?,
+            span,
+        }));
+    }
+
+    // Count the dots of a relative import; `...` is tokenized as one operator.
+    let mut level = 0;
+    let mut pos = 1;
+    while let Some(token) = tokens.get(pos).filter(|t| t.is_op(".") || t.is_op("...")) {
+        level += token.text.len();
+        pos += 1;
+    }
+    let keyword = pos + tokens[pos..].iter().position(|t| t.is_name("import"))?;
+    let module = &tokens[pos..keyword];
+    let module = match module {
+        [] if level > 0 => None,
+        _ => Some(dotted(module)?),
+    };
+    let mut names = &tokens[keyword + 1..];
+    if let [open, inner @ .., close] = names {
+        if open.is_op("(") && close.is_op(")") {
+            names = inner;
+        }
+    }
+    let names = match names {
+        [star] if star.is_op("*") => vec![Alias {
+            name: "*".to_string(),
+            asname: None,
+        }],
+        _ => aliases(names)?,
+    };
+    Some(Stmt::ImportFrom(ImportFrom {
+        module,
+        names,
+        level,
+        span,
+    }))
+}
+
+/// Parses a comma-separated list of `name [as alias]`.
+fn aliases(tokens: &[Token]) -> Option<Vec<Alias>> {
+    let mut aliases = vec![];
+    for part in split_list(tokens) {
+        let (name, asname) = match part {
+            [name @ .., keyword, alias]
+                if keyword.is_name("as") && alias.kind == TokenKind::Name =>
+            {
+                (name, Some(alias.text.clone()))
+            }
+            _ => (part, None),
+        };
+        aliases.push(Alias {
+            name: dotted(name)?,
+            asname,
+        });
+    }
+    (!aliases.is_empty()).then_some(aliases)
+}
+
+/// Joins the tokens of a dotted name, e.g., `pydantic.fields`.
+fn dotted(tokens: &[Token]) -> Option<String> {
+    let mut name = String::new();
+    for (i, token) in tokens.iter().enumerate() {
+        let expected = if i % 2 == 0 {
+            token.kind == TokenKind::Name
+        } else {
+            token.is_op(".")
+        };
+        if !expected {
+            return None;
+        }
+        name.push_str(&token.text);
+    }
+    (tokens.len() % 2 == 1).then_some(name)
+}
+
+/// Splits call (or class base list) arguments into positional arguments and
+/// keyword arguments.
+fn call_args(tokens: &[Token]) -> (Vec<Expr>, Vec<Keyword>) {
+    let mut args = vec![];
+    let mut keywords = vec![];
+    for part in split_list(tokens) {
+        if part.len() > 2 && part[0].kind == TokenKind::Name && part[1].is_op("=") {
+            keywords.push(Keyword {
+                arg: Some(part[0].text.clone()),
+                value: expr(&part[2..]),
+                span: span_of(part),
+            });
+        } else if part.len() > 1 && part[0].is_op("**") {
+            keywords.push(Keyword {
+                arg: None,
+                value: expr(&part[1..]),
+                span: span_of(part),
+            });
+        } else {
+            args.push(expr(part));
+        }
+    }
+    (args, keywords)
+}
+
+/// Parses an expression, falling back to `ExprKind::Other` for anything the
+/// tree does not model.
+pub(crate) fn expr(tokens: &[Token]) -> Expr {
+    let mut parser = ExprParser { tokens, pos: 0 };
+    match parser.binary(0) {
+        Some(expr) if parser.pos == tokens.len() => expr,
+        _ => new_expr(ExprKind::Other, tokens),
+    }
+}
+
+fn new_expr(kind: ExprKind, tokens: &[Token]) -> Expr {
+    Expr {
+        kind,
+        text: tokenizer::untokenize(tokens),
+        span: span_of(tokens),
+    }
+}
+
+struct ExprParser<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+}
+
+impl<'a> ExprParser<'a> {
+    fn peek(&self, n: usize) -> Option<&'a Token> {
+        self.tokens.get(self.pos + n)
+    }
+
+    fn finish(&self, kind: ExprKind, start: usize) -> Expr {
+        new_expr(kind, &self.tokens[start..self.pos])
+    }
+
+    /// Returns the binary operator at the cursor with its precedence and
+    /// length in tokens.
+    fn binary_op(&self) -> Option<(String, u8, usize)> {
+        let token = self.peek(0)?;
+        let (op, len) = match (token.kind, self.peek(1)) {
+            (TokenKind::Name, Some(next)) if token.text == "not" && next.is_name("in") => {
+                ("not in".to_string(), 2)
+            }
+            (TokenKind::Name, Some(next)) if token.text == "is" && next.is_name("not") => {
+                ("is not".to_string(), 2)
+            }
+            (TokenKind::Name | TokenKind::Op, _) => (token.text.clone(), 1),
+            _ => return None,
+        };
+        let (_, prec) = BINARY_OPERATORS.iter().find(|(o, _)| *o == op)?;
+        Some((op, *prec, len))
+    }
+
+    fn binary(&mut self, min_prec: u8) -> Option<Expr> {
+        let start = self.pos;
+        let mut left = self.unary()?;
+        while let Some((op, prec, len)) = self.binary_op() {
+            if prec < min_prec {
+                break;
+            }
+            self.pos += len;
+            // Exponentiation is right-associative.
+            let right = self.binary(if op == "**" { prec } else { prec + 1 })?;
+            left = self.finish(
+                ExprKind::BinOp {
+                    left: Box::new(left),
+                    op,
+                    right: Box::new(right),
+                },
+                start,
+            );
+        }
+        Some(left)
+    }
+
+    fn unary(&mut self) -> Option<Expr> {
+        let start = self.pos;
+        let token = self.peek(0)?;
+        let prec = if token.is_name("not") {
+            3
+        } else if token.is_op("-") || token.is_op("+") || token.is_op("~") {
+            11
+        } else {
+            return self.postfix();
+        };
+        let op = token.text.clone();
+        self.pos += 1;
+        let operand = self.binary(prec)?;
+        Some(self.finish(
+            ExprKind::UnaryOp {
+                op,
+                operand: Box::new(operand),
+            },
+            start,
+        ))
+    }
+
+    fn postfix(&mut self) -> Option<Expr> {
+        let start = self.pos;
+        let mut value = self.atom()?;
+        while let Some(token) = self.peek(0) {
+            let kind = if token.is_op(".") {
+                let attr = self.peek(1).filter(|t| t.kind == TokenKind::Name)?;
+                let attr = attr.text.clone();
+                self.pos += 2;
+                ExprKind::Attribute {
+                    value: Box::new(value),
+                    attr,
+                }
+            } else if token.is_op("(") {
+                let (inner, close) = enclosed(self.tokens, self.pos).ok()?;
+                let (args, keywords) = call_args(inner);
+                self.pos = close + 1;
+                ExprKind::Call {
+                    func: Box::new(value),
+                    args,
+                    keywords,
+                }
+            } else if token.is_op("[") {
+                let (inner, close) = enclosed(self.tokens, self.pos).ok()?;
+                self.pos = close + 1;
+                ExprKind::Subscript {
+                    value: Box::new(value),
+                    index: split_list(inner).into_iter().map(expr).collect(),
+                }
+            } else {
+                break;
+            };
+            value = self.finish(kind, start);
+        }
+        Some(value)
+    }
+
+    fn atom(&mut self) -> Option<Expr> {
+        let start = self.pos;
+        let token = self.peek(0)?;
+        let kind = match token.kind {
+            TokenKind::Name if ["True", "False", "None"].contains(&token.text.as_str()) => {
+                self.pos += 1;
+                ExprKind::Constant
+            }
+            TokenKind::Name
+                if RESERVED.contains(&token.text.as_str())
+                    || BINARY_OPERATORS.iter().any(|(op, _)| *op == token.text) =>
+            {
+                return None
+            }
+            TokenKind::Name => {
+                self.pos += 1;
+                ExprKind::Name(token.text.clone())
+            }
+            TokenKind::Number => {
+                self.pos += 1;
+                ExprKind::Constant
+            }
+            TokenKind::Str => {
+                // Adjacent string literals are concatenated.
+                while self.peek(0).is_some_and(|t| t.kind == TokenKind::Str) {
+                    self.pos += 1;
+                }
+                ExprKind::Constant
+            }
+            TokenKind::Op if token.text == "..." => {
+                self.pos += 1;
+                ExprKind::Constant
+            }
+            TokenKind::Op if ["(", "[", "{"].contains(&token.text.as_str()) => {
+                let open = token.text.clone();
+                let (inner, close) = enclosed(self.tokens, self.pos).ok()?;
+                self.pos = close + 1;
+                display(&open, inner)
+            }
+            _ => return None,
+        };
+        Some(self.finish(kind, start))
+    }
+}
+
+/// Parses the contents of a parenthesized expression or a list, set or dict
+/// display.
+fn display(open: &str, inner: &[Token]) -> ExprKind {
+    // Comprehensions and generator expressions are not modelled.
+    if contains_top_level(inner, |t| t.is_name("for")) {
+        return ExprKind::Other;
+    }
+    let items = split_list(inner);
+    match open {
+        "(" if items.len() == 1 && !inner.last().is_some_and(|t| t.is_op(",")) => expr(inner).kind,
+        "(" => ExprKind::Tuple(items.into_iter().map(expr).collect()),
+        "[" => ExprKind::List(items.into_iter().map(expr).collect()),
+        _ if items.is_empty() => ExprKind::Dict(vec![]),
+        _ if items
+            .iter()
+            .all(|item| contains_top_level(item, |t| t.is_op(":"))) =>
+        {
+            let mut pairs = vec![];
+            for item in items {
+                match split_once_top_level(item, ":") {
+                    (key, Some(value)) if !key.is_empty() && !value.is_empty() => {
+                        pairs.push((expr(key), expr(value)))
+                    }
+                    _ => return ExprKind::Other,
+                }
+            }
+            ExprKind::Dict(pairs)
+        }
+        _ => ExprKind::Set(items.into_iter().map(expr).collect()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::diagnostic::Severity;
+
+    fn parse_expr(source: &str) -> Expr {
+        let tokens = tokenizer::tokenize(source).unwrap();
+        expr(&tokens[..tokens.len() - 2])
+    }
+
+    fn first_class(source: &str) -> ClassDef {
+        match parse(source).unwrap().body.into_iter().next() {
+            Some(Stmt::ClassDef(class)) => class,
+            stmt => panic!("Expected class definition, found {:?}", stmt),
+        }
+    }
+
+    #[test]
+    fn test_parse_class_header() {
+        let class = first_class(concat!(
+            "@dataclass(frozen=True)\n",
+            "class Model(Base, typing.Generic[T], metaclass=Meta):\n",
+            "    pass\n",
+        ));
+        assert_eq!(class.name, "Model");
+        assert_eq!(class.decorators[0].text, "dataclass(frozen=True)");
+        assert_eq!(class.bases.len(), 2);
+        assert_eq!(class.bases[1].text, "typing.Generic[T]");
+        assert_eq!(class.keywords[0].arg.as_deref(), Some("metaclass"));
+        assert_eq!(class.span.line, 2);
+        assert!(matches!(class.body[..], [Stmt::Pass(_)]));
+    }
+
+    #[test]
+    fn test_parse_type_params() {
+        let class = first_class("class A[T: (int, str)](Base):\n    x: T\n");
+        assert_eq!(class.name, "A");
+        assert_eq!(class.bases[0].text, "Base");
+        assert!(matches!(class.body[..], [Stmt::AnnAssign(_)]));
+        let class = first_class("class B[T]:\n    def f[U](self, x: U) -> T: ...\n");
+        assert!(class.bases.is_empty());
+        let Stmt::FunctionDef(f) = &class.body[0] else {
+            panic!("Expected function definition");
+        };
+        assert_eq!(f.params.len(), 2);
+        assert_eq!(f.returns.as_ref().unwrap().text, "T");
+    }
+
+    #[test]
+    fn test_parse_imports() {
+        let module = parse(concat!(
+            "import pydantic as pd, typing\n",
+            "from ..shop.models import (\n",
+            "    Item as BaseItem,\n",
+            "    Order,\n",
+            ")\n",
+            "from . import models\n",
+            "from enum import *\n",
+            "from import x\n",
+        ))
+        .unwrap();
+        let alias = |name: &str, asname: Option<&str>| Alias {
+            name: name.to_string(),
+            asname: asname.map(str::to_string),
+        };
+        match &module.body[..] {
+            [Stmt::Import(import), Stmt::ImportFrom(relative), Stmt::ImportFrom(package), Stmt::ImportFrom(star), Stmt::Other(_)] =>
+            {
+                assert_eq!(
+                    import.names,
+                    [alias("pydantic", Some("pd")), alias("typing", None)]
+                );
+                assert_eq!(relative.module.as_deref(), Some("shop.models"));
+                assert_eq!(relative.level, 2);
+                assert_eq!(
+                    relative.names,
+                    [alias("Item", Some("BaseItem")), alias("Order", None)]
+                );
+                assert_eq!(package.module, None);
+                assert_eq!(package.level, 1);
+                assert_eq!(star.names, [alias("*", None)]);
+            }
+            body => panic!("Unexpected statements {:?}", body),
+        }
+    }
+
+    #[test]
+    fn test_parse_class_body() {
+        let class = first_class(concat!(
+            "class Model:\n",
+            "    \"\"\"Docstring.\"\"\"\n",
+            "    a: int\n",
+            "    b: str = 'x: y'\n",
+            "    c = d = 1\n",
+            "    RED\n",
+            "    class Config:\n",
+            "        frozen = True\n",
+            "    @property\n",
+            "    async def e(self, *args: int, f: int = 1, **kwargs) -> str:\n",
+            "        return 'e'\n",
+            "    if TYPE_CHECKING:\n",
+            "        g: int\n",
+        ));
+        let body = &class.body;
+        assert_eq!(body.len(), 8);
+        assert!(matches!(&body[0], Stmt::Expr(e) if matches!(e.kind, ExprKind::Constant)));
+        assert!(matches!(&body[1], Stmt::AnnAssign(a) if a.value.is_none()));
+        assert!(
+            matches!(&body[2], Stmt::AnnAssign(a) if a.value.as_ref().unwrap().text == "'x: y'")
+        );
+        assert!(matches!(&body[3], Stmt::Assign(a) if a.targets.len() == 2));
+        assert!(matches!(&body[4], Stmt::Expr(e) if matches!(e.kind, ExprKind::Name(_))));
+        assert!(matches!(&body[5], Stmt::ClassDef(c) if c.name == "Config" && c.body.len() == 1));
+        match &body[6] {
+            Stmt::FunctionDef(func) => {
+                assert!(func.is_async);
+                assert_eq!(func.decorators[0].text, "property");
+                assert_eq!(func.params.len(), 4);
+                assert_eq!(func.params[1].kind, ParamKind::VarPositional);
+                assert_eq!(func.params[2].default.as_ref().unwrap().text, "1");
+                assert_eq!(func.params[3].kind, ParamKind::VarKeyword);
+                assert_eq!(func.returns.as_ref().unwrap().text, "str");
+                assert_eq!(func.span.line, 10);
+            }
+            stmt => panic!("Expected function definition, found {:?}", stmt),
+        }
+        assert!(matches!(&body[7], Stmt::Other(_)));
+    }
+
+    #[test]
+    fn test_parse_expressions() {
+        let field = parse_expr("pydantic.Field(default=1, gt=0)");
+        match field.kind {
+            ExprKind::Call {
+                func,
+                args,
+                keywords,
+            } => {
+                assert_eq!(func.dotted_name().as_deref(), Some("pydantic.Field"));
+                assert!(args.is_empty());
+                assert_eq!(keywords.len(), 2);
+            }
+            kind => panic!("Expected call, found {:?}", kind),
+        }
+
+        let annotation = parse_expr("dict[str, list[int]] | None");
+        match annotation.kind {
+            ExprKind::BinOp { left, op, .. } => {
+                assert_eq!(op, "|");
+                assert!(
+                    matches!(left.kind, ExprKind::Subscript { ref index, .. } if index.len() == 2)
+                );
+            }
+            kind => panic!("Expected binary operation, found {:?}", kind),
+        }
+
+        assert!(matches!(parse_expr("{'a': 1}").kind, ExprKind::Dict(ref d) if d.len() == 1));
+        assert!(matches!(parse_expr("(1, 2)").kind, ExprKind::Tuple(_)));
+        assert!(matches!(parse_expr("lambda x: x").kind, ExprKind::Other));
+        assert!(matches!(parse_expr("[x for x in y]").kind, ExprKind::Other));
+        assert!(matches!(parse_expr("a if b else c").kind, ExprKind::Other));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse("class Model\n    pass\n").is_err());
+        assert!(parse("class Model:\n    def f(self) -> None\n").is_err());
+    }
+
+    #[test]
+    fn test_parse_lenient() {
+        let names = |body: &[Stmt]| {
+            body.iter()
+                .filter_map(|stmt| match stmt {
+                    Stmt::ClassDef(class) => Some(class.name.clone()),
+                    Stmt::FunctionDef(func) => Some(func.name.clone()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let (module, errors) = parse_lenient(concat!(
+            "class A(BaseModel:\n",
+            "    x: int\n",
+            "class B:\n",
+            "    def f(self) -> None\n",
+            "    def g(self): ...\n",
+            "class C\n",
+            "    y: int\n",
+            "class D: ...\n",
+        ));
+        assert_eq!(names(&module.body), ["B", "D"]);
+        let Stmt::ClassDef(b) = &module.body[0] else {
+            panic!("Expected class definition");
+        };
+        assert_eq!(names(&b.body), ["g"]);
+        assert!(matches!(
+            errors[..],
+            [
+                ScanError::UnterminatedBracket { .. },
+                ScanError::MissingColon { .. },
+                ScanError::MissingColon { .. },
+            ]
+        ));
+        assert_eq!(errors[0].span().line, 1);
+
+        // An unterminated docstring swallows the rest of the module.
+        let (module, errors) =
+            parse_lenient("class A: ...\nclass B:\n    \"\"\"Doc\nclass C: ...\n");
+        assert_eq!(names(&module.body), ["A", "B"]);
+        assert!(matches!(
+            errors[..],
+            [ScanError::UnterminatedDocstring { .. }]
+        ));
+
+        // Blanked lines keep the offsets of the lines after them.
+        let source = "s = 'éé\r\n)\r\n";
+        let (_, errors) = parse_lenient(source);
+        let span = errors[1].span();
+        assert_eq!((span.line, &source[span.start..span.end]), (2, ")"));
+        let source = "class Aé, metaclass=M):\r\n.\t'''d'''\r\n\t\tx: int\r\n";
+        let (_, errors) = parse_lenient(source);
+        assert!(!errors.is_empty());
+        for error in errors.iter() {
+            error.to_diagnostic(Severity::Warning, source).to_string();
+        }
+    }
+}
@@ -0,0 +1,210 @@
+use crate::{
+    consts,
+    relations::{self, ClassIndex, Edge},
+    scanner::{PyAttribute, PyClass, PyClassKind, PyMethodModifier, SignatureStyle},
+    RenderOptions, Renderer,
+};
+use std::{error::Error, io};
+
+pub struct ClassDiagram;
+impl ClassDiagram {
+    pub fn make(
+        models: &[PyClass],
+        options: &RenderOptions,
+        lines: &mut Vec<String>,
+    ) -> Result<(), &'static str> {
+        lines.push("@startuml".to_string());
+        // Qualified names such as `Outer.Inner` are plain class names here,
+        // not paths into packages.
+        lines.push("set separator none".to_string());
+        let index = ClassIndex::new(models);
+
+        // Classes are grouped into one package per Python module, in the
+        // order the modules were first seen.
+        for (module, group) in relations::group_by(models, |m| m.module.clone()) {
+            let indent = match &module {
+                Some(module) => {
+                    lines.push(format!("package {} {{", module));
+                    consts::INDENT
+                }
+                None => "",
+            };
+            for model in group {
+                Self::make_class(model, &index, indent, lines);
+            }
+            if module.is_some() {
+                lines.push("}".to_string());
+            }
+        }
+
+        for model in models.iter() {
+            for edge in relations::edges(model, &index) {
+                lines.push(match edge {
+                    Edge::Inherits { child, parent } => format!("{} <|-- {}", parent, child),
+                    Edge::Nests { outer, inner } => format!("{} +-- {}", outer, inner),
+                });
+            }
+        }
+        for relation in relations::relations(models, options).iter() {
+            lines.push(relation.to_uml(str::to_string));
+        }
+        lines.push("@enduml".to_string());
+        Ok(())
+    }
+
+    fn make_class(model: &PyClass, index: &ClassIndex, indent: &str, lines: &mut Vec<String>) {
+        let kind = index.kind(model);
+        let id = index.id(model);
+        let header = match (kind, kind.stereotype()) {
+            (PyClassKind::Enum, _) => format!("enum {}", id),
+            (_, Some(stereotype)) => format!("class {} <<{}>>", id, stereotype),
+            (_, None) => format!("class {}", id),
+        };
+        lines.push(format!("{}{} {{", indent, header));
+        for member in model.members.iter() {
+            lines.push(format!("{}{}{}", indent, consts::INDENT, member));
+        }
+        for attribute in model.attributes() {
+            let mut line = format!(
+                "{}{}{}{}",
+                indent,
+                consts::INDENT,
+                attribute.access().symbol(),
+                attribute.name()
+            );
+            if let Some(type_) = attribute.type_() {
+                line.push_str(&format!(" : {}", type_));
+            }
+            if let PyAttribute::Field(prop) = attribute {
+                if let Some(default) = prop.default_text() {
+                    line.push_str(&format!(" = {}", default));
+                }
+            }
+            lines.push(line);
+        }
+        for method in model.drawn_methods() {
+            let modifier = match method.modifier() {
+                Some(PyMethodModifier::Static) => "{static} ",
+                Some(PyMethodModifier::Abstract) => "{abstract} ",
+                None => "",
+            };
+            lines.push(format!(
+                "{}{}{}{}{}",
+                indent,
+                consts::INDENT,
+                modifier,
+                method.access.symbol(),
+                method.signature(&SignatureStyle::UML)
+            ));
+        }
+        lines.push(format!("{}}}", indent));
+
+        for child in model.children.iter() {
+            Self::make_class(child, index, indent, lines);
+        }
+    }
+}
+
+impl Renderer for ClassDiagram {
+    fn extension(&self) -> &str {
+        "puml"
+    }
+
+    fn render(
+        &self,
+        models: &[PyClass],
+        options: &RenderOptions,
+        out: &mut dyn io::Write,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut lines = vec![];
+        Self::make(models, options, &mut lines)?;
+        write!(out, "{}", lines.join("\r\n"))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::scanner;
+
+    fn render(source: &str, options: &RenderOptions) -> Vec<String> {
+        let mut lines = vec![];
+        let models = scanner::lex(source.to_string()).unwrap();
+        ClassDiagram::make(&models, options, &mut lines).unwrap();
+        lines
+    }
+
+    #[test]
+    fn test_class_diagram() {
+        let lines = render(
+            concat!(
+                "class Item(pydantic.BaseModel):\n",
+                "    id: int = 1\n",
+                "    def _check(self, value: int) -> bool: ...\n",
+                "class Special(Item):\n",
+                "    items: list[Item]\n",
+            ),
+            &RenderOptions::default(),
+        );
+        assert_eq!(
+            lines,
+            [
+                "@startuml",
+                "set separator none",
+                "class Item <<BaseModel>> {",
+                "    +id : int = 1",
+                "    -_check(value: int) : bool",
+                "}",
+                "class Special <<BaseModel>> {",
+                "    +items : list[Item]",
+                "}",
+                "pydantic.BaseModel <|-- Item",
+                "Item <|-- Special",
+                "Special *-- \"*\" Item : items",
+                "@enduml",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_stereotypes() {
+        let lines = render(
+            concat!(
+                "class Color(enum.Enum):\n",
+                "    RED = 'red'\n",
+                "@dataclass\n",
+                "class Point:\n",
+                "    x: int\n",
+                "class Outer:\n",
+                "    class Inner:\n",
+                "        pass\n",
+            ),
+            &RenderOptions::default(),
+        );
+        assert!(lines.contains(&"enum Color {".to_string()));
+        assert!(lines.contains(&"    RED = 'red'".to_string()));
+        assert!(lines.contains(&"class Point <<dataclass>> {".to_string()));
+        assert!(lines.contains(&"class Outer.Inner {".to_string()));
+        assert!(lines.contains(&"Outer +-- Outer.Inner".to_string()));
+    }
+
+    #[test]
+    fn test_packages() {
+        let mut models = scanner::lex("class A: ...\nclass B: ...\n".to_string()).unwrap();
+        models[0].module = Some("shop.models".to_string());
+        let mut lines = vec![];
+        ClassDiagram::make(&models, &RenderOptions::default(), &mut lines).unwrap();
+        assert_eq!(
+            lines[2..8],
+            [
+                "package shop.models {",
+                "    class A {",
+                "    }",
+                "}",
+                "class B {",
+                "}",
+            ]
+        );
+    }
+}
@@ -0,0 +1,718 @@
+use crate::{
+    ast::{Expr, ExprKind},
+    scanner::{PyClass, PyClassKind},
+    RenderOptions,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+/// Generic types holding any number of their type arguments.
+const COLLECTIONS: [&str; 16] = [
+    "list",
+    "List",
+    "set",
+    "Set",
+    "frozenset",
+    "FrozenSet",
+    "tuple",
+    "Tuple",
+    "Sequence",
+    "MutableSequence",
+    "Iterable",
+    "Iterator",
+    "Collection",
+    "AbstractSet",
+    "MutableSet",
+    "Deque",
+];
+
+/// Generic mapping types, whose last type argument is the value type.
+const MAPPINGS: [&str; 7] = [
+    "dict",
+    "Dict",
+    "Mapping",
+    "MutableMapping",
+    "DefaultDict",
+    "OrderedDict",
+    "Counter",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cardinality {
+    One,
+    ZeroOrOne,
+    Many,
+}
+
+impl Cardinality {
+    fn optional(self) -> Cardinality {
+        match self {
+            Cardinality::One => Cardinality::ZeroOrOne,
+            other => other,
+        }
+    }
+}
+
+impl fmt::Display for Cardinality {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Cardinality::One => write!(f, "1"),
+            Cardinality::ZeroOrOne => write!(f, "0..1"),
+            Cardinality::Many => write!(f, "*"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelationKind {
+    /// The source holds instances of the target by value, e.g., a pydantic
+    /// model nested in another.
+    Composition,
+    /// The source refers to instances of the target it does not own, e.g.,
+    /// a field holding an enum member.
+    Association,
+    /// The source uses the target in a method signature.
+    Dependency,
+}
+
+/// A relationship between two scanned classes, identified by their ids (see
+/// `ClassIndex::id`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Relation {
+    pub kind: RelationKind,
+    pub source: String,
+    pub target: String,
+    /// Name of the field the relationship is derived from.
+    pub label: Option<String>,
+    pub cardinality: Option<Cardinality>,
+}
+
+impl Relation {
+    /// Formats the relation in the arrow syntax shared by Mermaid and
+    /// PlantUML, e.g., `Order *-- "*" Item : items`, with class ids passed
+    /// through `id`.
+    pub(crate) fn to_uml(&self, id: impl Fn(&str) -> String) -> String {
+        let arrow = match self.kind {
+            RelationKind::Composition => "*--",
+            RelationKind::Association => "-->",
+            RelationKind::Dependency => "..>",
+        };
+        let mut line = format!("{} {}", id(&self.source), arrow);
+        if let Some(cardinality) = self.cardinality {
+            line.push_str(&format!(" \"{}\"", cardinality));
+        }
+        line.push_str(&format!(" {}", id(&self.target)));
+        if let Some(label) = &self.label {
+            line.push_str(&format!(" : {}", label));
+        }
+        line
+    }
+}
+
+/// A structural edge between two scanned classes, identified by their ids.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Edge {
+    /// `child` derives from `parent`, which may be a class that was not
+    /// scanned.
+    Inherits { child: String, parent: String },
+    /// `inner` is defined in the body of `outer`.
+    Nests { outer: String, inner: String },
+}
+
+/// Looks up scanned classes by the names under which they can be
+/// referenced, and names them uniquely within a diagram.
+pub(crate) struct ClassIndex<'a> {
+    classes: HashMap<String, &'a PyClass>,
+    /// Qualified names shared by classes of different modules.
+    ambiguous: HashSet<String>,
+    /// Names that refer to more than one class, and so resolve to none.
+    shared: HashSet<String>,
+}
+
+impl<'a> ClassIndex<'a> {
+    pub fn new(models: &'a [PyClass]) -> ClassIndex<'a> {
+        fn add<'a>(index: &mut ClassIndex<'a>, model: &'a PyClass) {
+            // Nested classes can also be referred to by their bare name, e.g.,
+            // `Inner` in the body of `Outer`.
+            for name in [&model.name, &model.qualname] {
+                let first = *index.classes.entry(name.clone()).or_insert(model);
+                if first.module != model.module || first.qualname != model.qualname {
+                    index.shared.insert(name.clone());
+                }
+            }
+            let first = index.classes[&model.qualname];
+            if first.module != model.module {
+                index.ambiguous.insert(model.qualname.clone());
+            }
+            if let Some(module) = &model.module {
+                index
+                    .classes
+                    .insert(format!("{}.{}", module, model.qualname), model);
+            }
+            for child in model.children.iter() {
+                add(index, child);
+            }
+        }
+        let mut index = ClassIndex {
+            classes: HashMap::new(),
+            ambiguous: HashSet::new(),
+            shared: HashSet::new(),
+        };
+        for model in models.iter() {
+            add(&mut index, model);
+        }
+        index
+    }
+
+    /// Names a class in a diagram: its qualified name, prefixed with its
+    /// module if another module has a class of the same name.
+    pub fn id(&self, model: &PyClass) -> String {
+        match &model.module {
+            Some(module) if self.ambiguous.contains(&model.qualname) => {
+                format!("{}.{}", module, model.qualname)
+            }
+            _ => model.qualname.clone(),
+        }
+    }
+
+    /// Names a base class of `model` in a diagram: the id of the scanned class
+    /// it refers to, or else its fully-qualified name.
+    pub fn base_id(&self, model: &PyClass, base: &Expr) -> String {
+        let Some(name) = base.dotted_name() else {
+            return base.text.clone();
+        };
+        match self.resolve_in(model, &name) {
+            Some(class) => self.id(class),
+            None => model.qualify(&name),
+        }
+    }
+
+    /// Resolves a (possibly module-qualified) class name, e.g.,
+    /// `models.MySecondModel`, to a scanned class. Names of classes that were
+    /// not scanned, and names shared by several classes, resolve to none.
+    pub fn resolve(&self, name: &str) -> Option<&'a PyClass> {
+        if self.shared.contains(name) {
+            return None;
+        }
+        self.classes.get(name).copied()
+    }
+
+    /// Resolves a class name as used in the module of `model`, following its
+    /// imports, e.g., `Base` after `from .models import Item as Base`. Names
+    /// not bound by an import are looked up in the module of `model` first.
+    pub fn resolve_in(&self, model: &PyClass, name: &str) -> Option<&'a PyClass> {
+        let qualified = model.qualify(name);
+        let local = match &model.module {
+            Some(module) if qualified == name => self.resolve(&format!("{}.{}", module, name)),
+            _ => None,
+        };
+        local.or_else(|| self.resolve(&qualified))
+    }
+
+    /// Determines the kind of a class, following its scanned base classes,
+    /// e.g., a subclass of a pydantic model is a model as well.
+    pub fn kind(&self, model: &PyClass) -> PyClassKind {
+        self.inherited_kind(model, &mut vec![])
+    }
+
+    fn inherited_kind(&self, model: &'a PyClass, seen: &mut Vec<&'a str>) -> PyClassKind {
+        let kind = model.kind();
+        if kind != PyClassKind::Plain || seen.contains(&model.qualname.as_str()) {
+            return kind;
+        }
+        seen.push(&model.qualname);
+        model
+            .parents
+            .iter()
+            .filter_map(|parent| self.resolve_in(model, &parent.dotted_name()?))
+            .map(|parent| self.inherited_kind(parent, seen))
+            .find(|kind| *kind != PyClassKind::Plain)
+            .unwrap_or_default()
+    }
+
+    /// Collects the ids of the scanned classes referenced by a type
+    /// annotation of `model` along with how many instances the annotation
+    /// holds.
+    pub fn references(&self, model: &PyClass, annotation: &Expr) -> Vec<(String, Cardinality)> {
+        let mut found = vec![];
+        self.collect(model, annotation, Cardinality::One, &mut found);
+        found
+    }
+
+    fn collect(
+        &self,
+        model: &PyClass,
+        expr: &Expr,
+        card: Cardinality,
+        found: &mut Vec<(String, Cardinality)>,
+    ) {
+        match &expr.kind {
+            ExprKind::Name(_) | ExprKind::Attribute { .. } => {
+                if let Some(target) = expr.dotted_name().and_then(|n| self.resolve_in(model, &n)) {
+                    found.push((self.id(target), card));
+                }
+            }
+            // Forward references, e.g., `"MySecondModel"`.
+            ExprKind::Constant => {
+                if let Some(target) = expr.string_value().and_then(|n| self.resolve_in(model, &n)) {
+                    found.push((self.id(target), card));
+                }
+            }
+            ExprKind::BinOp { left, op, right } if op == "|" => {
+                let card = if is_none(left) || is_none(right) {
+                    card.optional()
+                } else {
+                    card
+                };
+                self.collect(model, left, card, found);
+                self.collect(model, right, card, found);
+            }
+            ExprKind::Subscript { value, index } => {
+                let name = value.dotted_name().unwrap_or_default();
+                let base = name.rsplit('.').next().unwrap_or_default();
+                match base {
+                    "Optional" => {
+                        for arg in index.iter() {
+                            self.collect(model, arg, card.optional(), found);
+                        }
+                    }
+                    "Union" => {
+                        let card = if index.iter().any(is_none) {
+                            card.optional()
+                        } else {
+                            card
+                        };
+                        for arg in index.iter() {
+                            self.collect(model, arg, card, found);
+                        }
+                    }
+                    "Annotated" | "Required" | "NotRequired" | "ClassVar" | "Final" => {
+                        if let Some(arg) = index.first() {
+                            self.collect(model, arg, card, found);
+                        }
+                    }
+                    _ if COLLECTIONS.contains(&base) => {
+                        for arg in index.iter() {
+                            self.collect(model, arg, Cardinality::Many, found);
+                        }
+                    }
+                    _ if MAPPINGS.contains(&base) => {
+                        if let Some(arg) = index.last() {
+                            self.collect(model, arg, Cardinality::Many, found);
+                        }
+                    }
+                    // A scanned generic class, e.g., `Page[Item]`.
+                    _ => self.collect(model, value, card, found),
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn is_none(expr: &Expr) -> bool {
+    matches!(expr.kind, ExprKind::Constant) && expr.text == "None"
+}
+
+/// Groups classes by `key`, e.g., their module, in the order the groups were
+/// first seen.
+pub(crate) fn group_by<K: PartialEq>(
+    models: &[PyClass],
+    key: impl Fn(&PyClass) -> K,
+) -> Vec<(K, Vec<&PyClass>)> {
+    let mut groups: Vec<(K, Vec<&PyClass>)> = vec![];
+    for model in models.iter() {
+        let key = key(model);
+        match groups.iter_mut().find(|(other, _)| *other == key) {
+            Some((_, group)) => group.push(model),
+            None => groups.push((key, vec![model])),
+        }
+    }
+    groups
+}
+
+/// Collects the inheritance of a class and of the classes nested in it,
+/// along with the nesting itself.
+pub(crate) fn edges(model: &PyClass, index: &ClassIndex) -> Vec<Edge> {
+    fn add(model: &PyClass, index: &ClassIndex, edges: &mut Vec<Edge>) {
+        let id = index.id(model);
+        for parent in model.parents.iter() {
+            edges.push(Edge::Inherits {
+                child: id.clone(),
+                parent: index.base_id(model, parent),
+            });
+        }
+        for child in model.children.iter() {
+            edges.push(Edge::Nests {
+                outer: id.clone(),
+                inner: index.id(child),
+            });
+            add(child, index, edges);
+        }
+    }
+
+    let mut edges = vec![];
+    add(model, index, &mut edges);
+    edges
+}
+
+/// Derives the relations drawn between classes: those of their fields, and
+/// those of their methods if requested.
+pub fn relations(models: &[PyClass], options: &RenderOptions) -> Vec<Relation> {
+    let mut relations = field_relations(models);
+    if options.method_dependencies {
+        relations.extend(method_relations(models));
+    }
+    relations
+}
+
+/// Derives a composition between classes from every field annotated with
+/// another scanned class, including nested classes, or an association if the
+/// field holds an enum member.
+pub fn field_relations(models: &[PyClass]) -> Vec<Relation> {
+    fn add(index: &ClassIndex, model: &PyClass, relations: &mut Vec<Relation>) {
+        for prop in model.props.iter() {
+            let Some(annotation) = &prop.type_ else {
+                continue;
+            };
+            for (target, cardinality) in index.references(model, annotation) {
+                let kind = match index.resolve(&target).map(|target| index.kind(target)) {
+                    Some(PyClassKind::Enum) => RelationKind::Association,
+                    _ => RelationKind::Composition,
+                };
+                relations.push(Relation {
+                    kind,
+                    source: index.id(model),
+                    target,
+                    label: Some(prop.name.clone()),
+                    cardinality: Some(cardinality),
+                });
+            }
+        }
+        for child in model.children.iter() {
+            add(index, child, relations);
+        }
+    }
+
+    let index = ClassIndex::new(models);
+    let mut relations = vec![];
+    for model in models.iter() {
+        add(&index, model, &mut relations);
+    }
+    relations
+}
+
+/// Derives a dependency on every scanned class named in the parameter or
+/// return annotations of a class's methods. Each pair of classes is linked at
+/// most once, and classes referencing themselves are skipped.
+pub fn method_relations(models: &[PyClass]) -> Vec<Relation> {
+    fn add(index: &ClassIndex, model: &PyClass, relations: &mut Vec<Relation>) {
+        for method in model.methods.iter() {
+            let annotations = method
+                .params
+                .iter()
+                .filter_map(|param| param.type_.as_ref())
+                .chain(method.returns.iter());
+            for annotation in annotations {
+                for (target, _) in index.references(model, annotation) {
+                    let relation = Relation {
+                        kind: RelationKind::Dependency,
+                        source: index.id(model),
+                        target,
+                        label: None,
+                        cardinality: None,
+                    };
+                    if relation.source != relation.target && !relations.contains(&relation) {
+                        relations.push(relation);
+                    }
+                }
+            }
+        }
+        for child in model.children.iter() {
+            add(index, child, relations);
+        }
+    }
+
+    let index = ClassIndex::new(models);
+    let mut relations = vec![];
+    for model in models.iter() {
+        add(&index, model, &mut relations);
+    }
+    relations
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::scanner;
+
+    fn relations(source: &str) -> Vec<(String, String, String)> {
+        let models = scanner::lex(source.to_string()).unwrap();
+        field_relations(&models)
+            .into_iter()
+            .map(|r| {
+                (
+                    r.label.unwrap(),
+                    r.target,
+                    r.cardinality.unwrap().to_string(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_field_relations() {
+        // `models` is not imported, so `models.Item` is some other class.
+        let found = relations(concat!(
+            "class Item(pydantic.BaseModel):\n",
+            "    id: int\n",
+            "class Order(pydantic.BaseModel):\n",
+            "    item: Item\n",
+            "    backup: Optional[models.Item]\n",
+            "    items: list[Item]\n",
+            "    by_id: dict[str, Item] | None\n",
+            "    maybe: Item | None = None\n",
+            "    forward: 'Item'\n",
+            "    nested: Annotated[list[Optional[Item]], Field(max_length=3)]\n",
+            "    other: str\n",
+        ));
+        let expected = [
+            ("item", "1"),
+            ("items", "*"),
+            ("by_id", "*"),
+            ("maybe", "0..1"),
+            ("forward", "1"),
+            ("nested", "*"),
+        ]
+        .map(|(label, card)| (label.to_string(), "Item".to_string(), card.to_string()));
+        assert_eq!(found, expected);
+    }
+
+    #[test]
+    fn test_enum_relations() {
+        let models = scanner::lex(
+            concat!(
+                "class Color(enum.Enum):\n",
+                "    RED = 'red'\n",
+                "class Item(pydantic.BaseModel):\n",
+                "    color: Color | None\n",
+                "class Order(pydantic.BaseModel):\n",
+                "    items: list[Item]\n",
+            )
+            .to_string(),
+        )
+        .unwrap();
+        let kinds = field_relations(&models)
+            .into_iter()
+            .map(|r| (r.source, r.kind, r.target))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            [
+                (
+                    "Item".to_string(),
+                    RelationKind::Association,
+                    "Color".to_string()
+                ),
+                (
+                    "Order".to_string(),
+                    RelationKind::Composition,
+                    "Item".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_method_relations() {
+        let models = scanner::lex(
+            concat!(
+                "class Item:\n",
+                "    def copy(self) -> 'Item': ...\n",
+                "class Order:\n",
+                "    def add(self, item: Item, count: int) -> list[Item]: ...\n",
+                "    def remove(self, item: typing.Optional[Item]) -> None: ...\n",
+                "    def total(self) -> Decimal: ...\n",
+            )
+            .to_string(),
+        )
+        .unwrap();
+        let found = method_relations(&models)
+            .into_iter()
+            .map(|r| (r.source, r.target))
+            .collect::<Vec<_>>();
+        assert_eq!(found, vec![("Order".to_string(), "Item".to_string())]);
+    }
+
+    #[test]
+    fn test_inherited_kind() {
+        let models = scanner::lex(
+            concat!(
+                "class Base(pydantic.BaseModel): ...\n",
+                "class Child(Base): ...\n",
+                "class Grandchild(Child): ...\n",
+                "class Loop(Loop): ...\n",
+                "class External(models.Child): ...\n",
+            )
+            .to_string(),
+        )
+        .unwrap();
+        let index = ClassIndex::new(&models);
+        let kinds = models.iter().map(|m| index.kind(m)).collect::<Vec<_>>();
+        use PyClassKind::*;
+        assert_eq!(kinds, vec![Model, Model, Model, Plain, Plain]);
+    }
+
+    #[test]
+    fn test_ambiguous_names() {
+        let mut models = scanner::lex(
+            "class Item: ...\nclass Order:\n    item: Item\nclass Tax: ...\n".to_string(),
+        )
+        .unwrap();
+        let mut other = scanner::lex("class Item: ...\n".to_string()).unwrap();
+        for model in models.iter_mut() {
+            model.module = Some("shop".to_string());
+        }
+        other[0].module = Some("billing".to_string());
+        models.extend(other);
+
+        let index = ClassIndex::new(&models);
+        let ids = models.iter().map(|m| index.id(m)).collect::<Vec<_>>();
+        assert_eq!(ids, ["shop.Item", "Order", "Tax", "billing.Item"]);
+        let item = index.resolve("billing.Item").unwrap();
+        assert_eq!(item.module.as_deref(), Some("billing"));
+        assert_eq!(field_relations(&models)[0].target, "shop.Item");
+    }
+
+    #[test]
+    fn test_edges() {
+        let mut models = scanner::lex(
+            "class Base: ...\nclass Order(Base):\n    class Line(Base): ...\n".to_string(),
+        )
+        .unwrap();
+        let mut other = scanner::lex("class Tax(Base): ...\n".to_string()).unwrap();
+        models[1].module = Some("shop".to_string());
+        other[0].module = Some("billing".to_string());
+        models.extend(other);
+
+        let groups = group_by(&models, |m| m.module.clone())
+            .into_iter()
+            .map(|(module, group)| (module, group.len()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            groups,
+            [
+                (None, 1),
+                (Some("shop".to_string()), 1),
+                (Some("billing".to_string()), 1)
+            ]
+        );
+
+        let index = ClassIndex::new(&models);
+        assert_eq!(
+            edges(&models[1], &index),
+            [
+                Edge::Inherits {
+                    child: "Order".to_string(),
+                    parent: "Base".to_string()
+                },
+                Edge::Nests {
+                    outer: "Order".to_string(),
+                    inner: "Order.Line".to_string()
+                },
+                Edge::Inherits {
+                    child: "Order.Line".to_string(),
+                    parent: "Base".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_imported_names() {
+        let mut models = scanner::lex(
+            concat!(
+                "from . import orders\n",
+                "from .billing import Item as BillingItem\n",
+                "class Item(BillingItem):\n",
+                "    order: orders.Order\n",
+            )
+            .to_string(),
+        )
+        .unwrap();
+        let mut billing =
+            scanner::lex("class Item(pydantic.BaseModel): ...\n".to_string()).unwrap();
+        let mut orders = scanner::lex("class Order: ...\n".to_string()).unwrap();
+        billing[0].module = Some("billing".to_string());
+        orders[0].module = Some("orders".to_string());
+        models[0].module = Some("shop".to_string());
+        models.extend(billing);
+        models.extend(orders);
+
+        let index = ClassIndex::new(&models);
+        let shop = &models[0];
+        assert_eq!(index.base_id(shop, &shop.parents[0]), "billing.Item");
+        assert_eq!(index.kind(shop), PyClassKind::Model);
+        let relations = field_relations(&models);
+        assert_eq!(relations[0].source, "shop.Item");
+        assert_eq!(relations[0].target, "Order");
+    }
+
+    #[test]
+    fn test_same_name_packages() {
+        let root = std::env::temp_dir().join(format!("nereus-packages-{}", std::process::id()));
+        for package in ["shop", "billing"] {
+            std::fs::create_dir_all(root.join(package)).unwrap();
+            std::fs::write(root.join(package).join("__init__.py"), "").unwrap();
+            std::fs::write(
+                root.join(package).join("models.py"),
+                "class Item(pydantic.BaseModel): ...\n",
+            )
+            .unwrap();
+        }
+        std::fs::write(
+            root.join("shop").join("orders.py"),
+            concat!(
+                "from .models import Item\n",
+                "from external import Item as Other\n",
+                "from .missing import Item as Typo\n",
+                "class Order(Item):\n",
+                "    other: Other\n",
+                "    typo: Typo\n",
+            ),
+        )
+        .unwrap();
+        let files =
+            ["shop/models.py", "billing/models.py", "shop/orders.py"].map(|file| root.join(file));
+        let (models, _) = crate::scan_files(&files).unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+
+        let index = ClassIndex::new(&models);
+        let order = &models[2];
+        assert_eq!(index.base_id(order, &order.parents[0]), "shop.models.Item");
+        assert!(field_relations(&models).is_empty());
+        // A bare name shared by several classes names none of them.
+        assert!(index.resolve("Item").is_none());
+        assert!(index.resolve("billing.models.Item").is_some());
+    }
+
+    #[test]
+    fn test_nested_class_relations() {
+        let found = relations(concat!(
+            "class Outer:\n",
+            "    class Inner:\n",
+            "        pass\n",
+            "    inner: Inner\n",
+        ));
+        assert_eq!(
+            found,
+            vec![(
+                "inner".to_string(),
+                "Outer.Inner".to_string(),
+                "1".to_string()
+            )]
+        );
+    }
+}
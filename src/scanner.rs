@@ -1,522 +1,1621 @@
-use crate::consts;
-use std::collections::HashSet;
+pub use crate::diagnostic::ScanError;
+use crate::{
+    ast::{AnnAssign, ClassDef, Expr, ExprKind, FunctionDef, Module, ParamKind, Span, Stmt},
+    diagnostic::{Diagnostic, Severity},
+    parser,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    fmt, fs,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
 
-trait UniqueVec {
+pub(crate) trait UniqueVec {
     fn remove_dups(&mut self);
 }
 impl UniqueVec for Vec<PyClass> {
+    /// Keeps the first of several classes of the same name in one module.
     fn remove_dups(&mut self) {
         let mut found = HashSet::new();
-        self.retain(|cls| found.insert(cls.name.clone()));
+        self.retain(|cls| found.insert((cls.module.clone(), cls.name.clone())));
     }
 }
 
-trait PyLine {
-    fn is_docstring(&self) -> bool;
-    fn is_full_docstring(&self) -> bool;
-    fn is_placeholder(&self) -> bool;
-    fn is_decorator(&self) -> bool;
-    fn is_method(&self) -> bool;
-    fn is_class(&self) -> bool;
-    fn is_import(&self) -> bool;
-    fn is_comment(&self) -> bool;
-    fn indent_count(&self) -> usize;
-    fn starts_with_token(&self, token: &str) -> bool;
-    fn is_enum_variant(&self) -> bool;
-    fn get_declr_name(&self) -> Result<String, ScanError>;
+type PyType = Expr;
+type PyValue = Expr;
+type ClassName = String;
+
+/// Name of the attribute holding a pydantic v2 model's configuration.
+const CONFIG_ATTR: &str = "model_config";
+/// Name of the nested class holding a pydantic v1 model's configuration.
+const CONFIG_CLASS: &str = "Config";
+
+/// Base classes of pydantic models.
+const MODEL_BASES: [&str; 2] = ["BaseModel", "RootModel"];
+/// Base classes of enumerations.
+const ENUM_BASES: [&str; 5] = ["Enum", "IntEnum", "StrEnum", "Flag", "IntFlag"];
+/// Fully-qualified names of the attrs decorators making a class a dataclass.
+const ATTRS_DECORATORS: [&str; 9] = [
+    "attr.s",
+    "attr.attrs",
+    "attr.define",
+    "attr.frozen",
+    "attr.mutable",
+    "attr.dataclass",
+    "attrs.define",
+    "attrs.frozen",
+    "attrs.mutable",
+];
+/// Base class of msgspec structs.
+const STRUCT_BASE: &str = "msgspec.Struct";
+
+/// Keyword arguments of `Field(...)` that constrain a field's value.
+const FIELD_CONSTRAINTS: [&str; 7] = [
+    "gt",
+    "ge",
+    "lt",
+    "le",
+    "min_length",
+    "max_length",
+    "pattern",
+];
+
+#[derive(Debug, Clone)]
+pub struct PyParam {
+    pub name: String,
+    pub type_: Option<PyType>,
+    pub default: Option<PyValue>,
+    /// Metadata given through pydantic's `Field(...)`, either as the default
+    /// value or within an `Annotated[...]` type.
+    pub field: Option<PyField>,
+    pub span: Span,
 }
 
-impl PyLine for &str {
-    fn is_docstring(&self) -> bool {
-        let trimmed = self.trim();
-        trimmed.starts_with(DocstringMarker::SINGLE) || trimmed.starts_with(DocstringMarker::DOUBLE)
-    }
+/// Whether callers must supply a field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PyFieldKind {
+    Required,
+    /// Typed `Optional[X]` or `X | None` without a default.
+    Optional,
+    Defaulted,
+}
 
-    fn is_placeholder(&self) -> bool {
-        let trimmed = self.trim();
-        trimmed.starts_with(Placeholder::PASS) || trimmed.starts_with(Placeholder::ELLIPSIS)
+impl PyParam {
+    /// Returns the default value, looking through `Field(...)` unless the
+    /// field is assigned a plain value, e.g., `x: Annotated[int, Field()] = 5`.
+    /// An ellipsis (`...`) marks a field as required rather than defaulting
+    /// to it.
+    pub fn default_value(&self) -> Option<&PyValue> {
+        let default = match &self.field {
+            Some(field) if self.default.as_ref().is_none_or(is_field_call) => {
+                field.default.as_ref()
+            }
+            _ => self.default.as_ref(),
+        };
+        default.filter(|d| d.text != "...")
     }
 
-    fn is_decorator(&self) -> bool {
-        self.trim().starts_with("@")
+    pub fn default_factory(&self) -> Option<&PyValue> {
+        self.field.as_ref()?.default_factory.as_ref()
     }
 
-    fn starts_with_token(&self, token: &str) -> bool {
-        let parsed = self.trim().split(' ').nth(0);
-        parsed.is_some() && (parsed.unwrap() == token)
+    /// Describes the default of the field as drawn in a diagram: the default
+    /// value, or a call to the default factory, e.g., `list()`. Factories
+    /// that are not plain callables are shown as they are written, e.g.,
+    /// `<factory: lambda: 1>`.
+    pub fn default_text(&self) -> Option<String> {
+        if let Some(default) = self.default_value() {
+            return Some(default.text.clone());
+        }
+        let factory = self.default_factory()?;
+        match factory.kind {
+            ExprKind::Name(_) | ExprKind::Attribute { .. } => Some(format!("{}()", factory)),
+            _ => Some(format!("<factory: {}>", factory)),
+        }
     }
 
-    fn is_method(&self) -> bool {
-        self.starts_with_token("def")
+    pub fn kind(&self) -> PyFieldKind {
+        if self.default_value().is_some() || self.default_factory().is_some() {
+            PyFieldKind::Defaulted
+        } else if self.type_.as_ref().is_some_and(is_optional) {
+            PyFieldKind::Optional
+        } else {
+            PyFieldKind::Required
+        }
     }
+}
 
-    fn is_class(&self) -> bool {
-        self.starts_with_token("class")
+/// Whether a type annotation admits `None`, e.g., `Optional[int]`,
+/// `Union[int, None]` or `int | None`.
+fn is_optional(annotation: &Expr) -> bool {
+    match &annotation.kind {
+        ExprKind::Constant => annotation.text == "None",
+        ExprKind::Subscript { value, .. } if is_named(value, "Optional") => true,
+        ExprKind::Subscript { value, index } if is_named(value, "Union") => {
+            index.iter().any(is_optional)
+        }
+        ExprKind::Subscript { value, index } if is_named(value, "Annotated") => {
+            index.first().is_some_and(is_optional)
+        }
+        ExprKind::BinOp { left, op, right } if op == "|" => is_optional(left) || is_optional(right),
+        _ => false,
     }
+}
 
-    fn is_import(&self) -> bool {
-        let trimmed = self.trim();
-        trimmed.starts_with("import") || trimmed.starts_with("from")
-    }
+#[derive(Debug, Clone)]
+pub struct PyConstraint {
+    pub name: String,
+    pub value: PyValue,
+}
 
-    fn is_full_docstring(&self) -> bool {
-        let trimmed = self.trim();
-        (trimmed.starts_with(DocstringMarker::SINGLE)
-            && trimmed.ends_with(DocstringMarker::SINGLE)
-            && trimmed.len() >= 6)
-            || (trimmed.starts_with(DocstringMarker::DOUBLE)
-                && trimmed.ends_with(DocstringMarker::DOUBLE)
-                && trimmed.len() >= 6)
-    }
+#[derive(Debug, Default, Clone)]
+pub struct PyField {
+    pub default: Option<PyValue>,
+    pub default_factory: Option<PyValue>,
+    pub alias: Option<PyValue>,
+    pub title: Option<PyValue>,
+    pub description: Option<PyValue>,
+    pub constraints: Vec<PyConstraint>,
+    pub frozen: Option<PyValue>,
+    pub exclude: Option<PyValue>,
+}
 
-    fn is_comment(&self) -> bool {
-        self.trim().starts_with("#")
+impl PyField {
+    /// Lists the metadata that is set as `key=value` pairs, in declaration
+    /// order of the struct.
+    pub fn entries(&self) -> Vec<(&str, &PyValue)> {
+        let mut entries = vec![];
+        let options = [
+            ("default", &self.default),
+            ("default_factory", &self.default_factory),
+            ("alias", &self.alias),
+            ("title", &self.title),
+            ("description", &self.description),
+        ];
+        for (key, value) in options {
+            if let Some(value) = value {
+                entries.push((key, value));
+            }
+        }
+        for constraint in self.constraints.iter() {
+            entries.push((constraint.name.as_str(), &constraint.value));
+        }
+        for (key, value) in [("frozen", &self.frozen), ("exclude", &self.exclude)] {
+            if let Some(value) = value {
+                entries.push((key, value));
+            }
+        }
+        entries
     }
+}
 
-    fn is_enum_variant(&self) -> bool {
-        self.trim().chars().all(char::is_alphanumeric)
-    }
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PyMethodAccess {
+    #[default]
+    Public,
+    Private,
+}
 
-    fn indent_count(&self) -> usize {
-        self.split(consts::INDENT).filter(|s| s.is_empty()).count()
+impl PyMethodAccess {
+    /// Returns the UML visibility marker, e.g., `-` for private.
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            PyMethodAccess::Public => "+",
+            PyMethodAccess::Private => "-",
+        }
     }
+}
 
-    fn get_declr_name(&self) -> Result<String, ScanError> {
-        let term = '(';
-        if !(self.is_method() || self.is_class()) || !self.contains(term) {
-            return Err(ScanError(format!(
-                "Attempted to parse invalid declaration {}",
-                &self
-            )));
-        }
+/// Something drawn as a field of a class: a field, or a property, which is
+/// drawn as a field of the type its getter returns.
+#[derive(Debug, Clone, Copy)]
+pub enum PyAttribute<'a> {
+    Field(&'a PyParam),
+    Property(&'a PyMethod),
+}
 
-        let trimmed = self.replace(consts::INDENT, "");
-        if let Some(name) = trimmed.split(' ').nth(1) {
-            let end = name.find(term).unwrap();
-            return Ok(name[..end].to_string());
-        } else {
-            return Err(ScanError(format!(
-                "Failed to parse declaration '{}'. Invalid format.",
-                &trimmed
-            )));
+impl PyAttribute<'_> {
+    pub fn name(&self) -> &str {
+        match self {
+            PyAttribute::Field(prop) => &prop.name,
+            PyAttribute::Property(method) => &method.name,
         }
     }
-}
 
-fn split_string(string: String, token: char) -> Vec<String> {
-    let mut tokens: Vec<String> = vec![];
-    let mut current = String::new();
-    let mut iter = string.chars();
-    let mut pos: usize = 0;
-    loop {
-        match iter.next() {
-            Some(ch) => {
-                if ch == token {
-                    tokens.push(current.clone());
-                    current.clear();
-                } else if ch == '"' || ch == '\'' {
-                    let pystr = scan_pystr(&string[pos..]);
-                    for c in pystr.chars() {
-                        current.push(c);
-                        let _ = iter.next();
-                    }
-                } else {
-                    current.push(ch);
-                }
-            }
-            None => break,
+    pub fn access(&self) -> PyMethodAccess {
+        match self {
+            PyAttribute::Field(_) => PyMethodAccess::Public,
+            PyAttribute::Property(method) => method.access,
         }
-        pos += 1;
     }
-    tokens.push(current.clone());
-    tokens
-        .iter()
-        .map(|t| t.trim().to_string())
-        .collect::<Vec<String>>()
-}
-
-fn scan_pystr(line: &str) -> String {
-    let mut pystr = String::new();
-    let mut delims: Vec<char> = vec![];
-    for ch in line.chars() {
-        pystr.push(ch);
-        if ch == '\'' || ch == '"' {
-            // If last quote matches current, we've closed that substr.
-            if let Some(d) = delims.last() {
-                if ch == *d {
-                    delims.pop();
-                }
-                if delims.is_empty() {
-                    break;
-                }
-            // If not, we're scanning a new substr.
-            } else {
-                delims.push(ch);
-            }
+
+    pub fn type_(&self) -> Option<&PyValue> {
+        match self {
+            PyAttribute::Field(prop) => prop.type_.as_ref(),
+            PyAttribute::Property(method) => method.returns.as_ref(),
         }
     }
-    pystr
 }
 
-type PyType = String;
-type PyValue = String;
-type ClassName = String;
+/// How a method is bound, as set by its decorators.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum PyMethodKind {
+    #[default]
+    Instance,
+    Class,
+    Static,
+    /// The getter of a property, e.g., `@property` or `@cached_property`.
+    Property,
+    /// The setter or deleter of a property, e.g., `@name.setter`.
+    PropertyAccessor,
+}
 
-struct DocstringMarker;
-impl DocstringMarker {
-    const SINGLE: &str = "'''";
-    const DOUBLE: &str = "\"\"\"";
+/// A UML modifier of a drawn method. A method is drawn with one at most, a
+/// static method being static first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PyMethodModifier {
+    Static,
+    Abstract,
 }
 
-struct Placeholder;
-impl Placeholder {
-    const PASS: &str = "pass";
-    const ELLIPSIS: &str = "...";
+/// How the signature of a method is punctuated in a diagram.
+#[derive(Debug, Clone, Copy)]
+pub struct SignatureStyle {
+    /// Puts the type of a parameter before its name, e.g., `int x`, rather
+    /// than after it, e.g., `x: int`.
+    pub type_first: bool,
+    /// Precedes the return type, e.g., ` : ` in `area() : float`.
+    pub returns: &'static str,
 }
 
-#[derive(Debug, Clone)]
-pub struct PyParam {
-    pub name: String,
-    pub type_: Option<PyType>,
-    pub default: Option<PyValue>,
+impl SignatureStyle {
+    /// The UML notation, e.g., `scale(factor: float) : None`.
+    pub const UML: SignatureStyle = SignatureStyle {
+        type_first: false,
+        returns: " : ",
+    };
 }
 
-#[derive(Default, Debug, Clone)]
-pub enum PyMethodAccess {
-    #[default]
-    Public,
-    Private,
+/// The pydantic decorator that turns a method into a hook.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PyHookKind {
+    /// A pydantic v1 `@validator`.
+    Validator,
+    FieldValidator,
+    /// A `@model_validator`, or a pydantic v1 `@root_validator`.
+    ModelValidator,
+    FieldSerializer,
+    ModelSerializer,
+    ComputedField,
+}
+
+/// A method pydantic calls while validating or serializing a model, e.g.,
+/// one decorated with `@field_validator("id")`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PyHook {
+    pub kind: PyHookKind,
+    /// Names of the fields the hook applies to, where `*` stands for all
+    /// fields. Empty for hooks applying to the whole model.
+    pub fields: Vec<String>,
+}
+
+impl PyHook {
+    pub fn is_serializer(&self) -> bool {
+        matches!(
+            self.kind,
+            PyHookKind::FieldSerializer | PyHookKind::ModelSerializer
+        )
+    }
+
+    pub fn applies_to(&self, field: &str) -> bool {
+        self.fields.iter().any(|name| name == field || name == "*")
+    }
 }
 
 #[derive(Clone, Debug)]
 pub struct PyMethod {
     pub name: String,
+    pub decorators: Vec<Expr>,
     pub params: Vec<PyParam>,
     pub returns: Option<PyType>,
     pub access: PyMethodAccess,
+    pub span: Span,
 }
 
 impl PyMethod {
     pub fn is_dunder(&self) -> bool {
         self.name.starts_with("__") && self.name.ends_with("__")
     }
+
+    pub fn kind(&self) -> PyMethodKind {
+        let names = self.decorator_names().collect::<Vec<_>>();
+        let is_any = |expected: &[&str]| names.iter().any(|name| expected.contains(name));
+        if is_any(&["setter", "deleter"]) {
+            PyMethodKind::PropertyAccessor
+        } else if is_any(&[
+            "property",
+            "cached_property",
+            "abstractproperty",
+            "computed_field",
+        ]) {
+            PyMethodKind::Property
+        } else if is_any(&["staticmethod"]) {
+            PyMethodKind::Static
+        } else if is_any(&["classmethod"]) {
+            PyMethodKind::Class
+        } else {
+            PyMethodKind::Instance
+        }
+    }
+
+    pub fn is_abstract(&self) -> bool {
+        self.decorator_names()
+            .any(|name| name == "abstractmethod" || name == "abstractproperty")
+    }
+
+    /// Returns how pydantic uses the method, if it is a validator, serializer
+    /// or computed field, along with the fields named in its decorator.
+    pub fn hook(&self) -> Option<PyHook> {
+        self.decorators.iter().find_map(|decorator| {
+            let name = callee(decorator).dotted_name()?;
+            let kind = match name.rsplit('.').next()? {
+                "validator" => PyHookKind::Validator,
+                "field_validator" => PyHookKind::FieldValidator,
+                "model_validator" | "root_validator" => PyHookKind::ModelValidator,
+                "field_serializer" => PyHookKind::FieldSerializer,
+                "model_serializer" => PyHookKind::ModelSerializer,
+                "computed_field" => PyHookKind::ComputedField,
+                _ => return None,
+            };
+            let fields = match &decorator.kind {
+                ExprKind::Call { args, .. } => {
+                    args.iter().filter_map(|arg| arg.string_value()).collect()
+                }
+                _ => vec![],
+            };
+            Some(PyHook { kind, fields })
+        })
+    }
+
+    pub fn modifier(&self) -> Option<PyMethodModifier> {
+        if self.kind() == PyMethodKind::Static {
+            Some(PyMethodModifier::Static)
+        } else if self.is_abstract() {
+            Some(PyMethodModifier::Abstract)
+        } else {
+            None
+        }
+    }
+
+    /// Formats the name, explicit parameters and return type of the method,
+    /// e.g., `scale(factor: float) : None`.
+    pub fn signature(&self, style: &SignatureStyle) -> String {
+        let params = self
+            .explicit_params()
+            .iter()
+            .map(|param| match &param.type_ {
+                Some(type_) if style.type_first => format!("{} {}", type_, param.name),
+                Some(type_) => format!("{}: {}", param.name, type_),
+                None => param.name.clone(),
+            })
+            .collect::<Vec<String>>();
+        let mut signature = format!("{}({})", self.name, params.join(", "));
+        if let Some(returns) = &self.returns {
+            signature.push_str(&format!("{}{}", style.returns, returns));
+        }
+        signature
+    }
+
+    /// Returns the parameters callers pass, i.e., all but the implicit `self`
+    /// or `cls` of methods that are not static.
+    pub fn explicit_params(&self) -> &[PyParam] {
+        let implicit = self.kind() != PyMethodKind::Static
+            && self
+                .params
+                .first()
+                .is_some_and(|param| !param.name.starts_with('*') && param.name != "/");
+        &self.params[usize::from(implicit)..]
+    }
+
+    /// Last segments of the names of the method's decorators, e.g.,
+    /// `abstractmethod` for `@abc.abstractmethod`.
+    fn decorator_names(&self) -> impl Iterator<Item = &str> {
+        self.decorators.iter().filter_map(|decorator| {
+            let name = match &callee(decorator).kind {
+                ExprKind::Name(name) => name,
+                ExprKind::Attribute { attr, .. } => attr,
+                _ => return None,
+            };
+            Some(name.as_str())
+        })
+    }
+}
+
+/// A member of an enumeration, e.g., `RED = "red"` or `RED = auto()`.
+#[derive(Debug, Clone)]
+pub struct PyEnumMember {
+    pub name: String,
+    pub value: Option<PyValue>,
+    pub span: Span,
+}
+
+impl fmt::Display for PyEnumMember {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.value {
+            Some(value) => write!(f, "{} = {}", self.name, value),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
+
+/// A class configuration option, set through pydantic v2's `model_config =
+/// ConfigDict(...)`, in a nested pydantic v1 `Config` class, as an argument
+/// of a dataclass decorator, e.g., `@dataclass(frozen=True)`, or as a class
+/// keyword, e.g., `class Point(msgspec.Struct, frozen=True)`.
+#[derive(Debug, Clone)]
+pub struct PyConfigEntry {
+    pub key: String,
+    pub value: PyValue,
+    pub span: Span,
 }
 
 #[derive(Debug, Default, Clone)]
 pub struct PyClass {
     pub name: ClassName,
-    pub parents: Vec<ClassName>,
+    /// Dotted path of the class within its module, e.g.,
+    /// `MyFirstModel.Config` for a nested class.
+    pub qualname: ClassName,
+    /// Dotted path of the module the class was scanned from, if known.
+    pub module: Option<String>,
+    /// File the class was scanned from, if known.
+    pub file: Option<PathBuf>,
+    pub decorators: Vec<Expr>,
+    pub parents: Vec<PyType>,
+    /// Names bound at the top level of the class's module by imports and
+    /// class definitions, mapped to the fully-qualified names they refer to.
+    /// Shared by all classes of the module.
+    pub names: Rc<HashMap<String, String>>,
     pub props: Vec<PyParam>,
+    /// Members of an enumeration, which are not listed in `props`.
+    pub members: Vec<PyEnumMember>,
     pub methods: Vec<PyMethod>,
+    /// Classes defined in the body of this class.
+    pub children: Vec<PyClass>,
+    pub config: Vec<PyConfigEntry>,
+    pub span: Span,
+}
+
+/// What a class is used for, as far as nereus can tell.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum PyClassKind {
+    #[default]
+    Plain,
+    /// A pydantic model.
+    Model,
+    /// A dataclass of the standard library, pydantic or attrs.
+    Dataclass,
+    Enum,
+    TypedDict,
+    NamedTuple,
+    /// A msgspec struct.
+    Struct,
 }
 
-#[derive(Debug)]
-pub struct ScanError(String);
-impl std::fmt::Display for ScanError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.0)
+impl PyClassKind {
+    /// Names the kind in a diagram, e.g., `<<dataclass>>` in Mermaid.
+    pub fn stereotype(&self) -> Option<&'static str> {
+        match self {
+            PyClassKind::Plain => None,
+            PyClassKind::Model => Some("BaseModel"),
+            PyClassKind::Dataclass => Some("dataclass"),
+            PyClassKind::Enum => Some("enumeration"),
+            PyClassKind::TypedDict => Some("TypedDict"),
+            PyClassKind::NamedTuple => Some("NamedTuple"),
+            PyClassKind::Struct => Some("Struct"),
+        }
     }
 }
-impl std::error::Error for ScanError {}
 
-pub fn lex(source: String) -> Result<Vec<PyClass>, ScanError> {
-    let mut models = vec![];
-    let mut i = 0;
-
-    // Split and filter out ignorable lines.
-    let lines = source
-        .split("\n")
-        .filter(|s| {
-            !s.is_empty()
-                && !s.is_import()
-                && !s.is_comment()
-                && !s.is_full_docstring()
-                && !s.is_decorator()
+impl PyClass {
+    /// Determines the kind of class from its own decorators and base
+    /// classes. See `relations::ClassIndex::kind` to also take scanned base
+    /// classes into account.
+    pub fn kind(&self) -> PyClassKind {
+        // Compare the last segment of the qualified names, so that both
+        // `pydantic.BaseModel` and an aliased `from pydantic import BaseModel
+        // as Model` are recognized.
+        let names = |exprs: &[Expr], strip: fn(&Expr) -> &Expr| {
+            exprs
+                .iter()
+                .filter_map(|expr| strip(expr).dotted_name())
+                .map(|name| self.qualify(&name))
+                .collect::<Vec<String>>()
+        };
+        let is_any = |names: &[String], expected: &[&str]| {
+            names.iter().any(|name| {
+                let last = name.rsplit('.').next().unwrap_or_default();
+                expected.contains(&last)
+            })
+        };
+        let bases = names(&self.parents, generic_base);
+        if self.dataclass_decorator().is_some() {
+            PyClassKind::Dataclass
+        } else if is_any(&bases, &ENUM_BASES) {
+            PyClassKind::Enum
+        } else if is_any(&bases, &MODEL_BASES) {
+            PyClassKind::Model
+        } else if bases.iter().any(|base| base == STRUCT_BASE) {
+            PyClassKind::Struct
+        } else if is_any(&bases, &["TypedDict"]) {
+            PyClassKind::TypedDict
+        } else if is_any(&bases, &["NamedTuple"]) {
+            PyClassKind::NamedTuple
+        } else {
+            PyClassKind::Plain
+        }
+    }
+
+    /// Returns the decorator making the class a dataclass, if any, e.g.,
+    /// `@dataclass(frozen=True)` or `@attrs.define`.
+    fn dataclass_decorator(&self) -> Option<&Expr> {
+        self.decorators.iter().find(|decorator| {
+            let Some(name) = callee(decorator).dotted_name() else {
+                return false;
+            };
+            let name = self.qualify(&name);
+            name.rsplit('.').next() == Some("dataclass")
+                || ATTRS_DECORATORS.contains(&name.as_str())
         })
-        .collect::<Vec<_>>();
+    }
+
+    /// Returns what is drawn as the fields of the class: its fields, followed
+    /// by its properties.
+    pub fn attributes(&self) -> impl Iterator<Item = PyAttribute<'_>> {
+        let properties = self
+            .methods
+            .iter()
+            .filter(|method| method.kind() == PyMethodKind::Property);
+        self.props
+            .iter()
+            .map(PyAttribute::Field)
+            .chain(properties.map(PyAttribute::Property))
+    }
 
-    // NOTE: Whitespace is significant in Python
-    while i < lines.len() {
-        let line = lines[i];
-
-        // Module-level docstrings.
-        if line.is_docstring() {
-            skip_multiline_docstring(&lines, &mut i);
-        // Ignore all module-level statements and expressions that aren't
-        // class definitions.
-        } else if !(line.is_class() && line.indent_count() == 0) {
-            i += 1;
-        // Ignore all other lines.
+    /// Returns the methods drawn as such, i.e., all but dunder methods and
+    /// those of properties, which are drawn as fields.
+    pub fn drawn_methods(&self) -> impl Iterator<Item = &PyMethod> {
+        self.methods.iter().filter(|method| {
+            !method.is_dunder()
+                && !matches!(
+                    method.kind(),
+                    PyMethodKind::Property | PyMethodKind::PropertyAccessor
+                )
+        })
+    }
+
+    /// Returns the methods validating or serializing the field `name`, e.g.,
+    /// those decorated with `@field_validator("name")`.
+    pub fn hooks_of(&self, name: &str) -> Vec<(&PyMethod, PyHook)> {
+        self.methods
+            .iter()
+            .filter_map(|method| Some((method, method.hook()?)))
+            .filter(|(_, hook)| hook.applies_to(name))
+            .collect()
+    }
+
+    /// Resolves a dotted name used in the class's module through its imports,
+    /// e.g., `pd.BaseModel` to `pydantic.BaseModel` after `import pydantic as
+    /// pd`. Names that are not bound by an import or a class definition, such
+    /// as builtins, are returned unchanged.
+    pub fn qualify(&self, name: &str) -> String {
+        let (head, rest) = match name.split_once('.') {
+            Some((head, rest)) => (head, Some(rest)),
+            None => (name, None),
+        };
+        match (self.names.get(head), rest) {
+            (Some(target), Some(rest)) => format!("{}.{}", target, rest),
+            (Some(target), None) => target.clone(),
+            (None, _) => name.to_string(),
+        }
+    }
+
+    /// Returns the dotted path of the package the class was scanned from,
+    /// e.g., `shop.orders` for a class in `shop/orders/models.py` or in
+    /// `shop/orders/__init__.py`.
+    pub fn package(&self) -> Option<String> {
+        let module = self.module.as_ref()?;
+        let file = self.file.as_ref().and_then(|file| file.file_stem());
+        if file.is_some_and(|stem| stem == "__init__") {
+            Some(module.clone())
         } else {
-            let mut class_name = line.split(' ').nth(1).unwrap();
-            let mut props: Vec<PyParam> = vec![];
-            let mut methods: Vec<PyMethod> = vec![];
-
-            // Scan class names, including those of super classes.
-            let mut parents: Vec<String> = vec![];
-            match line.find('(') {
-                Some(start) => {
-                    let end = line.find(")").unwrap();
-                    let parent_args = &line[start + 1..end];
-                    parents = parent_args
-                        .split(",")
-                        .map(|p| p.trim().to_string())
-                        .collect::<Vec<String>>();
-                    class_name = &line[..start].split(' ').nth(1).unwrap();
+            module
+                .rsplit_once('.')
+                .map(|(package, _)| package.to_string())
+        }
+    }
+
+    /// Records where the class, and the classes nested in it, were scanned
+    /// from.
+    fn set_origin(&mut self, module: &str, file: &Path) {
+        self.module = Some(module.to_string());
+        self.file = Some(file.to_path_buf());
+        for child in self.children.iter_mut() {
+            child.set_origin(module, file);
+        }
+    }
+}
+
+/// Returns the function called by `expr`, or `expr` itself if it is not a
+/// call, e.g., `dataclass` for both `@dataclass` and `@dataclass(frozen=True)`.
+fn callee(expr: &Expr) -> &Expr {
+    match &expr.kind {
+        ExprKind::Call { func, .. } => func,
+        _ => expr,
+    }
+}
+
+/// Strips the type arguments of a generic base class, e.g., `RootModel` for
+/// `RootModel[list[int]]`.
+fn generic_base(expr: &Expr) -> &Expr {
+    match &expr.kind {
+        ExprKind::Subscript { value, .. } => value,
+        _ => expr,
+    }
+}
+
+/// Scans the classes of Python source. Notes about statements that were
+/// skipped are dropped; `lex_lenient` and `lex_file` report them.
+pub fn lex(source: String) -> Result<Vec<PyClass>, ScanError> {
+    let ast = parser::parse(&source)?;
+    let (models, _) = scan_module(&ast, &source, None, None);
+    Ok(models)
+}
+
+/// Scans Python source like `lex`, but skips what cannot be scanned instead
+/// of failing, reporting each construct skipped as a warning.
+pub fn lex_lenient(source: String) -> (Vec<PyClass>, Vec<Diagnostic>) {
+    let (ast, errors) = parser::parse_lenient(&source);
+    let mut diagnostics = errors
+        .iter()
+        .map(|err| err.to_diagnostic(Severity::Warning, &source))
+        .collect::<Vec<_>>();
+    let (models, notes) = scan_module(&ast, &source, None, None);
+    diagnostics.extend(notes);
+    (models, diagnostics)
+}
+
+/// Scans the classes of a module, along with notes about the statements in
+/// them that were skipped. The module's dotted path and package, if known,
+/// are used to resolve relative imports and to qualify the names of classes
+/// defined in it.
+fn scan_module(
+    ast: &Module,
+    source: &str,
+    module: Option<&str>,
+    package: Option<&str>,
+) -> (Vec<PyClass>, Vec<Diagnostic>) {
+    let names = Rc::new(scan_names(&ast.body, module, package));
+    let mut diagnostics = vec![];
+
+    // Ignore all module-level statements and expressions that aren't class
+    // definitions or imports.
+    let mut models = ast
+        .body
+        .iter()
+        .filter_map(|stmt| match stmt {
+            Stmt::ClassDef(class) => {
+                Some(scan_class(class, None, &names, source, &mut diagnostics))
+            }
+            _ => None,
+        })
+        .collect::<Vec<PyClass>>();
+    models.remove_dups();
+    (models, diagnostics)
+}
+
+/// Maps the names bound by the imports and class definitions of a module to
+/// the fully-qualified names they refer to. Later bindings override earlier
+/// ones, as they do in Python.
+fn scan_names(
+    body: &[Stmt],
+    module: Option<&str>,
+    package: Option<&str>,
+) -> HashMap<String, String> {
+    let mut names = HashMap::new();
+    for stmt in body.iter() {
+        match stmt {
+            // `import a.b` binds `a`, which needs no resolving.
+            Stmt::Import(import) => {
+                for alias in import.names.iter() {
+                    if let Some(asname) = &alias.asname {
+                        names.insert(asname.clone(), alias.name.clone());
+                    }
                 }
-                None => {
-                    if let Some(term) = line.find(':') {
-                        class_name = &line[..term].split(' ').nth(1).unwrap();
-                    } else {
-                        return Err(ScanError(format!(
-                            "Failed to identify class name terminator (:) in class {}",
-                            class_name
-                        )));
+            }
+            Stmt::ImportFrom(import) => {
+                // `from ..x import a` in package `p.q` imports `p.x.a`.
+                let mut parts = match import.level {
+                    0 => vec![],
+                    level => {
+                        let package = package.unwrap_or_default().split('.');
+                        let package = package.filter(|part| !part.is_empty()).collect::<Vec<_>>();
+                        package[..package.len().saturating_sub(level - 1)].to_vec()
                     }
+                };
+                parts.extend(import.module.as_deref());
+                for alias in import.names.iter().filter(|alias| alias.name != "*") {
+                    let target = parts
+                        .iter()
+                        .copied()
+                        .chain([alias.name.as_str()])
+                        .collect::<Vec<_>>()
+                        .join(".");
+                    let name = alias.asname.as_ref().unwrap_or(&alias.name);
+                    names.insert(name.clone(), target);
                 }
             }
+            Stmt::ClassDef(class) => {
+                if let Some(module) = module {
+                    names.insert(class.name.clone(), format!("{}.{}", module, class.name));
+                }
+            }
+            _ => {}
+        }
+    }
+    names
+}
 
-            i += 1;
+/// Scans a Python source file, recording the file and module of every class
+/// found, along with notes about the statements that were skipped.
+pub fn lex_file(path: &Path) -> Result<(Vec<PyClass>, Vec<Diagnostic>), Box<dyn Error>> {
+    let source = read_source(path)?;
+    let ast = parser::parse(&source).map_err(|err| err.render(&source, Some(path)))?;
+    Ok(scan_file(&ast, &source, path))
+}
 
-            // Scan class namespace.
-            while i < lines.len() && lines[i].indent_count() > 0 {
-                let line = lines[i];
-                if line.indent_count() > 1 {
-                    // Ignore statements and expressions scoped to, e.g.,
-                    // methods.
-                    while i < lines.len() && lines[i].indent_count() > 1 {
-                        i += 1;
-                    }
-                } else if line.is_class() && line.indent_count() == 0 {
-                    break;
-                } else if line.is_method() {
-                    methods.push(scan_method(&lines, &mut i)?);
-                } else if line.is_docstring() {
-                    skip_multiline_docstring(&lines, &mut i);
-                } else if line.is_placeholder() {
-                    i += 1;
-                // TODO: handle field access
-                } else if line.contains(":") && !line.is_class() {
-                    let field_and_type: Vec<&str> =
-                        line.split([':', '=']).map(|s| s.trim()).collect();
-                    props.push(PyParam {
-                        name: field_and_type[0].to_string(),
-                        type_: Some(field_and_type[1].to_string()),
-                        default: None,
-                    });
-                    i += 1;
-                } else if line.contains("=") {
-                    let field_and_type: Vec<&str> = line.split('=').map(|s| s.trim()).collect();
-                    props.push(PyParam {
-                        name: field_and_type[0].to_string(),
-                        type_: None,
-                        default: Some(field_and_type[1].to_string()),
-                    });
-                    i += 1;
-                } else if line.is_enum_variant() {
+/// Scans a Python source file like `lex_file`, but skips what cannot be
+/// scanned instead of failing, reporting each construct skipped as a
+/// warning. Only a file that cannot be read is an error.
+pub fn lex_file_lenient(path: &Path) -> Result<(Vec<PyClass>, Vec<Diagnostic>), Box<dyn Error>> {
+    let source = read_source(path)?;
+    let (ast, errors) = parser::parse_lenient(&source);
+    let mut diagnostics = errors
+        .iter()
+        .map(|err| err.to_diagnostic(Severity::Warning, &source).in_file(path))
+        .collect::<Vec<_>>();
+    let (models, notes) = scan_file(&ast, &source, path);
+    diagnostics.extend(notes);
+    Ok((models, diagnostics))
+}
+
+fn read_source(path: &Path) -> Result<String, String> {
+    fs::read_to_string(path).map_err(|err| format!("{}: {}", path.display(), err))
+}
+
+fn scan_file(ast: &Module, source: &str, path: &Path) -> (Vec<PyClass>, Vec<Diagnostic>) {
+    let module = module_name(path);
+    let package = match path.file_stem() {
+        Some(stem) if stem == "__init__" => Some(module.as_str()),
+        _ => module.rsplit_once('.').map(|(package, _)| package),
+    };
+    let (mut models, diagnostics) = scan_module(ast, source, Some(&module), package);
+    for model in models.iter_mut() {
+        model.set_origin(&module, path);
+    }
+    let diagnostics = diagnostics
+        .into_iter()
+        .map(|diagnostic| diagnostic.in_file(path))
+        .collect();
+    (models, diagnostics)
+}
+
+/// Derives the dotted module path of a source file from the packages, i.e.,
+/// directories with an `__init__.py`, that contain it. For example,
+/// `src/shop/orders/models.py` is `shop.orders.models` if both `shop` and
+/// `orders` are packages.
+pub fn module_name(path: &Path) -> String {
+    let mut parts = vec![];
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    if stem != "__init__" {
+        parts.push(stem.to_string());
+    }
+    let mut dir = path.parent();
+    while let Some(package) = dir.filter(|dir| dir.join("__init__.py").is_file()) {
+        match package.file_name() {
+            Some(name) => parts.push(name.to_string_lossy().to_string()),
+            None => break,
+        }
+        dir = package.parent();
+    }
+    parts.reverse();
+    parts.join(".")
+}
+
+fn scan_class(
+    class: &ClassDef,
+    outer: Option<&str>,
+    names: &Rc<HashMap<String, String>>,
+    source: &str,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> PyClass {
+    let qualname = match outer {
+        Some(outer) => format!("{}.{}", outer, class.name),
+        None => class.name.clone(),
+    };
+    let mut props: Vec<PyParam> = vec![];
+    let mut methods: Vec<PyMethod> = vec![];
+    let mut children: Vec<PyClass> = vec![];
+    let mut config: Vec<PyConfigEntry> = vec![];
+
+    for stmt in class.body.iter() {
+        match stmt {
+            Stmt::FunctionDef(func) => methods.push(scan_method(func)),
+            Stmt::ClassDef(nested) => {
+                let child = scan_class(nested, Some(&qualname), names, source, diagnostics);
+                if child.name == CONFIG_CLASS {
+                    config.extend(child.props.iter().filter_map(|prop| {
+                        Some(PyConfigEntry {
+                            key: prop.name.clone(),
+                            value: prop.default.clone()?,
+                            span: prop.span,
+                        })
+                    }));
+                }
+                children.push(child);
+            }
+            Stmt::AnnAssign(AnnAssign {
+                target,
+                value: Some(value),
+                ..
+            }) if target.text == CONFIG_ATTR => config.extend(scan_config(value)),
+            Stmt::Assign(assign) if assign.targets.iter().any(|t| t.text == CONFIG_ATTR) => {
+                config.extend(scan_config(&assign.value))
+            }
+            // TODO: handle field access
+            Stmt::AnnAssign(assign) => props.push(PyParam {
+                name: assign.target.text.clone(),
+                type_: Some(assign.annotation.clone()),
+                default: assign.value.clone(),
+                field: scan_field(Some(&assign.annotation), assign.value.as_ref()),
+                span: assign.span,
+            }),
+            Stmt::Assign(assign) => {
+                for target in assign.targets.iter() {
                     props.push(PyParam {
-                        name: line.trim().to_string(),
+                        name: target.text.clone(),
                         type_: None,
-                        default: None,
+                        default: Some(assign.value.clone()),
+                        field: scan_field(None, Some(&assign.value)),
+                        span: assign.span,
                     });
-                    i += 1;
-                } else {
-                    println!("Skipping unscannable line {}", line);
-                    i += 1;
                 }
             }
+            Stmt::Expr(Expr {
+                kind: ExprKind::Name(variant),
+                span,
+                ..
+            }) => props.push(PyParam {
+                name: variant.clone(),
+                type_: None,
+                default: None,
+                field: None,
+                span: *span,
+            }),
+            // Docstrings, placeholders and imports local to the class.
+            Stmt::Expr(_) | Stmt::Pass(_) | Stmt::Import(_) | Stmt::ImportFrom(_) => {}
+            Stmt::Other(span) => diagnostics.push(
+                Diagnostic::new(Severity::Note, "skipped a statement that cannot be scanned")
+                    .at(*span, source),
+            ),
+        }
+    }
 
-            models.push(PyClass {
-                name: class_name.to_string(),
-                parents,
-                props,
-                methods,
+    let mut model = PyClass {
+        name: class.name.clone(),
+        qualname,
+        module: None,
+        file: None,
+        decorators: class.decorators.clone(),
+        parents: class.bases.clone(),
+        names: names.clone(),
+        props,
+        members: vec![],
+        methods,
+        children,
+        config,
+        span: class.span,
+    };
+
+    // Enum members are assigned like fields, but names starting with an
+    // underscore, e.g., `_ignore_`, and bare annotations are not members.
+    if model.kind() == PyClassKind::Enum {
+        let (members, props): (Vec<_>, Vec<_>) = std::mem::take(&mut model.props)
+            .into_iter()
+            .partition(|prop| {
+                !prop.name.starts_with('_') && (prop.type_.is_none() || prop.default.is_some())
+            });
+        model.props = props;
+        model.members = members
+            .into_iter()
+            .map(|prop| PyEnumMember {
+                name: prop.name,
+                value: prop.default,
+                span: prop.span,
             })
-        }
+            .collect();
     }
-    models.remove_dups();
-    Ok(models)
+
+    // Options given in the class header come before those in its body.
+    let mut options = model
+        .dataclass_decorator()
+        .map(scan_config)
+        .unwrap_or_default();
+    options.extend(class.keywords.iter().filter_map(|keyword| {
+        let key = keyword.arg.clone().filter(|arg| arg != "metaclass")?;
+        Some(PyConfigEntry {
+            key,
+            value: keyword.value.clone(),
+            span: keyword.span,
+        })
+    }));
+    model.config.splice(0..0, options);
+    model
 }
 
-fn scan_bounded(
-    left: char,
-    lines: &Vec<&str>,
-    curr_pos: &mut usize,
-    inclusive: bool,
-) -> Result<String, ScanError> {
-    let right = match left {
-        '(' => ')',
-        '{' => '}',
-        '[' => ']',
-        _ => panic!("Received unhandled boundary token {}", left),
+/// Scans the metadata of a field declared through a call to `Field(...)` as
+/// its default value or in `Annotated[type, Field(...)]`.
+fn scan_field(annotation: Option<&Expr>, value: Option<&Expr>) -> Option<PyField> {
+    let annotated = match annotation.map(|a| &a.kind) {
+        Some(ExprKind::Subscript { value, index }) if is_named(value, "Annotated") => {
+            index.iter().skip(1).find(|meta| is_field_call(meta))
+        }
+        _ => None,
+    };
+    let call = value.filter(|v| is_field_call(v)).or(annotated)?;
+    let ExprKind::Call { args, keywords, .. } = &call.kind else {
+        return None;
     };
-    let l_pos = lines[*curr_pos].find(left);
-    if l_pos.is_none() {
-        return Err(ScanError(format!(
-            "Left boundary token '{}' not found in line '{}'",
-            left, lines[*curr_pos]
-        )));
-    }
-
-    let start = *curr_pos;
-    let mut inside: Vec<&str> = vec![];
-    loop {
-        let line = if *curr_pos == start {
-            &lines[*curr_pos][l_pos.unwrap() + 1..]
-        } else {
-            lines[*curr_pos]
-        };
 
-        if let Some(r_pos) = line.find(right) {
-            inside.push(&line[..r_pos]);
-            break;
-        } else {
-            inside.push(line);
+    let mut field = PyField {
+        default: args.first().cloned(),
+        ..Default::default()
+    };
+    for keyword in keywords.iter() {
+        let value = Some(keyword.value.clone());
+        match keyword.arg.as_deref() {
+            Some("default") => field.default = value,
+            Some("default_factory") => field.default_factory = value,
+            Some("alias") => field.alias = value,
+            Some("title") => field.title = value,
+            Some("description") => field.description = value,
+            Some("frozen") => field.frozen = value,
+            Some("exclude") => field.exclude = value,
+            Some(name) if FIELD_CONSTRAINTS.contains(&name) => {
+                field.constraints.push(PyConstraint {
+                    name: name.to_string(),
+                    value: keyword.value.clone(),
+                })
+            }
+            _ => {}
         }
+    }
+    Some(field)
+}
 
-        *curr_pos += 1;
-        if *curr_pos == lines.len() {
-            return Err(ScanError(
-                "Failed to scan bounded lexeme. Right boundary (closing) token '{}' not found."
-                    .to_string(),
-            ));
-        }
+/// Whether `expr` names `name`, either directly or as a module attribute,
+/// e.g., `Field` or `pydantic.Field`.
+fn is_named(expr: &Expr, name: &str) -> bool {
+    match &expr.kind {
+        ExprKind::Name(n) => n == name,
+        ExprKind::Attribute { attr, .. } => attr == name,
+        _ => false,
     }
+}
 
-    let mut joined = inside
-        .iter()
-        .map(|line| {
-            let mut line_ = line.trim().to_string();
-            if line_.ends_with(',') {
-                line_.push_str(" ");
-            }
-            line_
-        })
-        .collect::<Vec<String>>()
-        .join("");
-
-    if inclusive {
-        joined = format!("{}{}{}", right, joined, left);
-    }
-    Ok(joined)
-}
-
-fn scan_method(lines: &Vec<&str>, curr_pos: &mut usize) -> Result<PyMethod, ScanError> {
-    // TODO: remove below after testing.
-    // Remove consts::INDENT and trailing spaces.
-    let signature = lines[*curr_pos];
-    let name = signature.get_declr_name()?;
-    let mut params: Vec<PyParam> = vec![];
-    let mut returns: Option<PyType> = Option::None;
-
-    let param_str = scan_bounded('(', lines, curr_pos, false)?;
-    for param in split_string(param_str, ',') {
-        let mut param_and_default = split_string(param, '=').into_iter();
-        let name_and_type = param_and_default.next();
-        let default = param_and_default.next();
-
-        let mut s = split_string(name_and_type.unwrap(), ':').into_iter();
-        let param_name = s.next();
-        let type_ = s.next();
-
-        params.push(PyParam {
-            name: param_name.unwrap(),
-            type_,
-            default,
-        });
-    }
-
-    // Closing parenthesis and terminating token (colon, :) are always
-    // found on the same line.
-    // Likewise for return annotations.
-    let line = lines[*curr_pos];
-    if let Some(p) = line.find(')') {
-        let r = line[p + 1..]
-            .replace(")", "")
-            .replace("->", "")
-            .replace(":", "")
-            .trim()
-            .to_string();
-        if !r.is_empty() {
-            returns = Option::Some(r);
-        }
-    } else {
-        panic!(
-            "Reached invalid line {} after parameter parse. Expected closing parenthesis ')'",
-            line
-        )
+fn is_field_call(expr: &Expr) -> bool {
+    matches!(&expr.kind, ExprKind::Call { func, .. } if is_named(func, "Field"))
+}
+
+/// Scans the options passed to `ConfigDict(...)` or given as a dict literal.
+fn scan_config(value: &Expr) -> Vec<PyConfigEntry> {
+    match &value.kind {
+        ExprKind::Call { keywords, .. } => keywords
+            .iter()
+            .filter_map(|keyword| {
+                Some(PyConfigEntry {
+                    key: keyword.arg.clone()?,
+                    value: keyword.value.clone(),
+                    span: keyword.span,
+                })
+            })
+            .collect(),
+        ExprKind::Dict(pairs) => pairs
+            .iter()
+            .filter_map(|(key, value)| {
+                Some(PyConfigEntry {
+                    key: key.string_value()?,
+                    value: value.clone(),
+                    span: key.span.to(value.span),
+                })
+            })
+            .collect(),
+        _ => vec![],
     }
+}
 
-    *curr_pos += 1;
+fn scan_method(func: &FunctionDef) -> PyMethod {
+    let params = func
+        .params
+        .iter()
+        .map(|param| PyParam {
+            name: match param.kind {
+                ParamKind::VarPositional => format!("*{}", param.name),
+                ParamKind::VarKeyword => format!("**{}", param.name),
+                _ => param.name.clone(),
+            },
+            type_: param.annotation.clone(),
+            default: param.default.clone(),
+            field: None,
+            span: param.span,
+        })
+        .collect();
 
-    Ok(PyMethod {
-        name: name.to_string(),
+    PyMethod {
+        name: func.name.clone(),
+        decorators: func.decorators.clone(),
         params,
-        returns,
-        access: if name.starts_with('_') {
+        returns: func.returns.clone(),
+        access: if func.name.starts_with('_') {
             PyMethodAccess::Private
         } else {
             PyMethodAccess::Public
         },
-    })
-}
-
-fn skip_multiline_docstring(lines: &Vec<&str>, curr_pos: &mut usize) {
-    *curr_pos += 1;
-    while !(lines[*curr_pos].trim().ends_with(DocstringMarker::SINGLE)
-        || lines[*curr_pos].trim().ends_with(DocstringMarker::DOUBLE))
-    {
-        *curr_pos += 1;
+        span: func.span,
     }
-    *curr_pos += 1;
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
 
+    fn scan_first_method(source: &str) -> PyMethod {
+        let models = lex(format!("class Model:\n{}", source)).unwrap();
+        models[0].methods[0].clone()
+    }
+
+    fn text(expr: &Option<Expr>) -> Option<&str> {
+        expr.as_ref().map(|e| e.text.as_str())
+    }
+
     #[test]
     fn test_same_line_method_scan() {
-        let lines = vec![
-            "    def my_method(self, value: typing.Any):",
-            "        print(value)",
-        ];
-        let mut pos = 0;
-        let _ = scan_method(&lines, &mut pos);
-        assert_eq!(pos, 1);
+        let method = scan_first_method(
+            "    def my_method(self, value: typing.Any):\n        print(value)\n",
+        );
+        assert_eq!(method.params.len(), 2);
+        assert_eq!(text(&method.params[1].type_), Some("typing.Any"));
+        assert!(method.returns.is_none());
     }
 
     #[test]
     fn test_listed_arg_method_scan() {
-        let lines = vec![
-            "    def my_method(",
-            "        self,",
-            "        value: typing.Any = 'my, default'",
-            "    ) -> list[str | tuple[str, str]]:",
-            "        return ['hello world!']",
-        ];
-        let mut pos = 0;
-        let m = scan_method(&lines, &mut pos);
-        dbg!(m.unwrap());
-        assert_eq!(pos, 4);
+        let method = scan_first_method(concat!(
+            "    def my_method(\n",
+            "        self,\n",
+            "        value: typing.Any = 'my, default'\n",
+            "    ) -> list[str | tuple[str, str]]:\n",
+            "        return ['hello world!']\n",
+        ));
+        assert_eq!(method.params.len(), 2);
+        assert_eq!(text(&method.params[1].default), Some("'my, default'"));
+        assert_eq!(text(&method.returns), Some("list[str | tuple[str, str]]"));
     }
 
     #[test]
     fn test_staggered_arg_method_scan() {
-        let lines = vec![
-            "    def my_method(self,",
-            "        value: typing.Any) -> None:",
-            "        print(value)",
-        ];
-        let mut pos = 0;
-        let _ = scan_method(&lines, &mut pos);
-        assert_eq!(pos, 2);
+        let method = scan_first_method(concat!(
+            "    def my_method(self,\n",
+            "        value: typing.Any) -> None:\n",
+            "        print(value)\n",
+        ));
+        assert_eq!(method.params[1].name, "value");
+        assert_eq!(text(&method.returns), Some("None"));
+        assert_eq!(method.span.line, 2);
+    }
+
+    #[test]
+    fn test_method_kinds() {
+        let models = lex(concat!(
+            "class Shape(abc.ABC):\n",
+            "    @property\n",
+            "    def name(self) -> str: ...\n",
+            "    @name.setter\n",
+            "    def name(self, value: str) -> None: ...\n",
+            "    @staticmethod\n",
+            "    def unit(scale: float) -> 'Shape': ...\n",
+            "    @classmethod\n",
+            "    def create(cls, *args) -> 'Shape': ...\n",
+            "    @abc.abstractmethod\n",
+            "    def area(self) -> float: ...\n",
+            "    def scale(self, factor: float) -> None: ...\n",
+        )
+        .to_string())
+        .unwrap();
+        let methods = &models[0].methods;
+        use PyMethodKind::*;
+        let kinds = methods.iter().map(|m| m.kind()).collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            [
+                Property,
+                PropertyAccessor,
+                Static,
+                Class,
+                Instance,
+                Instance
+            ]
+        );
+        assert!(methods[4].is_abstract());
+        assert!(!methods[5].is_abstract());
+        let params = |method: &PyMethod| {
+            method
+                .explicit_params()
+                .iter()
+                .map(|param| param.name.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(params(&methods[2]), ["scale"]);
+        assert_eq!(params(&methods[3]), ["*args"]);
+        assert_eq!(params(&methods[5]), ["factor"]);
+
+        let drawn = models[0]
+            .drawn_methods()
+            .map(|m| (m.modifier(), m.signature(&SignatureStyle::UML)))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            drawn,
+            [
+                (
+                    Some(PyMethodModifier::Static),
+                    "unit(scale: float) : 'Shape'".to_string()
+                ),
+                (None, "create(*args) : 'Shape'".to_string()),
+                (
+                    Some(PyMethodModifier::Abstract),
+                    "area() : float".to_string()
+                ),
+                (None, "scale(factor: float) : None".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_method_hooks() {
+        let models = lex(concat!(
+            "class User(pydantic.BaseModel):\n",
+            "    id: int\n",
+            "    name: str\n",
+            "    @validator('id')\n",
+            "    def check_id(cls, value): ...\n",
+            "    @pydantic.field_validator('id', 'name', mode='before')\n",
+            "    @classmethod\n",
+            "    def strip(cls, value): ...\n",
+            "    @field_validator('*')\n",
+            "    def check_all(cls, value): ...\n",
+            "    @model_validator(mode='after')\n",
+            "    def check(self): ...\n",
+            "    @field_serializer('name')\n",
+            "    def dump_name(self, value): ...\n",
+            "    @computed_field\n",
+            "    @property\n",
+            "    def label(self) -> str: ...\n",
+            "    def plain(self): ...\n",
+        )
+        .to_string())
+        .unwrap();
+        let model = &models[0];
+        let hooks = model
+            .methods
+            .iter()
+            .map(|method| method.hook())
+            .collect::<Vec<_>>();
+        let hook = |kind, fields: &[&str]| {
+            Some(PyHook {
+                kind,
+                fields: fields.iter().map(|name| name.to_string()).collect(),
+            })
+        };
+        assert_eq!(
+            hooks,
+            [
+                hook(PyHookKind::Validator, &["id"]),
+                hook(PyHookKind::FieldValidator, &["id", "name"]),
+                hook(PyHookKind::FieldValidator, &["*"]),
+                hook(PyHookKind::ModelValidator, &[]),
+                hook(PyHookKind::FieldSerializer, &["name"]),
+                hook(PyHookKind::ComputedField, &[]),
+                None,
+            ]
+        );
+        assert_eq!(model.methods[5].kind(), PyMethodKind::Property);
+        let names = |field| {
+            model
+                .hooks_of(field)
+                .into_iter()
+                .map(|(method, _)| method.name.as_str())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names("id"), ["check_id", "strip", "check_all"]);
+        assert_eq!(names("name"), ["strip", "check_all", "dump_name"]);
+    }
+
+    #[test]
+    fn test_variadic_params() {
+        let method = scan_first_method("    def my_method(self, *args, **kwargs): ...\n");
+        let names = method
+            .params
+            .iter()
+            .map(|p| p.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["self", "*args", "**kwargs"]);
+    }
+
+    #[test]
+    fn test_field_defaults_with_colons() {
+        let models = lex(concat!(
+            "class Model(pydantic.BaseModel):\n",
+            "    url: str = 'http://example.com'\n",
+            "    mapping: dict[str, int] = {'a': 1}\n",
+            "    key = lambda x: x\n",
+        )
+        .to_string())
+        .unwrap();
+        let props = &models[0].props;
+        assert_eq!(props[0].name, "url");
+        assert_eq!(text(&props[0].type_), Some("str"));
+        assert_eq!(text(&props[0].default), Some("'http://example.com'"));
+        assert_eq!(text(&props[1].type_), Some("dict[str, int]"));
+        assert_eq!(text(&props[1].default), Some("{'a': 1}"));
+        assert_eq!(props[2].name, "key");
+        assert!(props[2].type_.is_none());
+        assert_eq!(text(&props[2].default), Some("lambda x: x"));
+    }
+
+    #[test]
+    fn test_soft_keyword_fields() {
+        let models = lex(concat!(
+            "class Cat(pydantic.BaseModel):\n",
+            "    type: Literal['cat']\n",
+            "    match: bool = False\n",
+            "    type = 'cat'\n",
+            "    type Id = int\n",
+            "    match x:\n",
+            "        case _: pass\n",
+        )
+        .to_string())
+        .unwrap();
+        let props = &models[0].props;
+        let names = props.iter().map(|p| p.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, ["type", "match", "type"]);
+        assert_eq!(text(&props[0].type_), Some("Literal['cat']"));
+        assert_eq!(text(&props[1].default), Some("False"));
+    }
+
+    #[test]
+    fn test_indentation_styles() {
+        let source = concat!(
+            "class Model(pydantic.BaseModel):\n",
+            "    id: int\n",
+            "    def method(self) -> None:\n",
+            "        if self.id:\n",
+            "            print(self.id)\n",
+            "    name: str\n",
+        );
+        let scan = |source: String| {
+            let models = lex(source).unwrap();
+            let props = models[0].props.iter().map(|p| p.name.clone());
+            let methods = models[0].methods.iter().map(|m| m.name.clone());
+            props.chain(methods).collect::<Vec<_>>()
+        };
+        let expected = scan(source.to_string());
+        assert_eq!(expected, vec!["id", "name", "method"]);
+        assert_eq!(scan(source.replace("    ", "  ")), expected);
+        assert_eq!(scan(source.replace("    ", "\t")), expected);
+    }
+
+    #[test]
+    fn test_nested_classes() {
+        let models = lex(concat!(
+            "class Model(pydantic.BaseModel):\n",
+            "    class Config:\n",
+            "        frozen = True\n",
+            "    class Inner:\n",
+            "        class Innermost:\n",
+            "            id: int\n",
+        )
+        .to_string())
+        .unwrap();
+        let model = &models[0];
+        assert!(model.props.is_empty());
+        assert_eq!(model.children.len(), 2);
+        assert_eq!(model.children[0].qualname, "Model.Config");
+        assert_eq!(
+            model.children[1].children[0].qualname,
+            "Model.Inner.Innermost"
+        );
+        assert_eq!(model.config[0].key, "frozen");
+        assert_eq!(model.config[0].value.text, "True");
+    }
+
+    #[test]
+    fn test_field_metadata() {
+        let models = lex(concat!(
+            "class Model(pydantic.BaseModel):\n",
+            "    id: pydantic.StrictInt = pydantic.Field(default=1)\n",
+            "    name: str = Field('x', alias='Name', min_length=1, frozen=True)\n",
+            "    tags: Annotated[list[str], Field(default_factory=list, max_length=3)]\n",
+            "    plain: int = 1\n",
+        )
+        .to_string())
+        .unwrap();
+        let props = &models[0].props;
+
+        let id = props[0].field.as_ref().unwrap();
+        assert_eq!(text(&id.default), Some("1"));
+
+        let name = props[1].field.as_ref().unwrap();
+        assert_eq!(text(&name.default), Some("'x'"));
+        assert_eq!(text(&name.alias), Some("'Name'"));
+        assert_eq!(text(&name.frozen), Some("True"));
+        assert_eq!(name.constraints[0].name, "min_length");
+
+        let tags = props[2].field.as_ref().unwrap();
+        assert_eq!(text(&tags.default_factory), Some("list"));
+        let entries = tags
+            .entries()
+            .into_iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect::<Vec<_>>();
+        assert_eq!(entries, vec!["default_factory=list", "max_length=3"]);
+
+        assert!(props[3].field.is_none());
+    }
+
+    #[test]
+    fn test_field_kinds() {
+        let models = lex(concat!(
+            "class Model(pydantic.BaseModel):\n",
+            "    a: str\n",
+            "    b: Optional[str]\n",
+            "    c: int | None\n",
+            "    d: typing.Union[None, int]\n",
+            "    e: str = 'hello'\n",
+            "    f: Optional[str] = None\n",
+            "    g: int = Field(default=1)\n",
+            "    h: list[int] = Field(default_factory=list)\n",
+            "    i: int = Field(..., gt=0)\n",
+            "    j: int = ...\n",
+            "    k: Annotated[int, Field(gt=0)] = 5\n",
+            "    l: Annotated[int, Field(gt=0)]\n",
+        )
+        .to_string())
+        .unwrap();
+        use PyFieldKind::*;
+        let kinds = models[0].props.iter().map(|p| p.kind()).collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            vec![
+                Required, Optional, Optional, Optional, Defaulted, Defaulted, Defaulted, Defaulted,
+                Required, Required, Defaulted, Required
+            ]
+        );
+        assert_eq!(
+            models[0].props[6].default_value().map(|d| d.text.as_str()),
+            Some("1")
+        );
+        assert_eq!(
+            models[0].props[10].default_value().map(|d| d.text.as_str()),
+            Some("5")
+        );
     }
 
     #[test]
-    fn test_declaration_name() {
-        let signature = "    def my_method(self,";
-        let declaration = signature.get_declr_name().unwrap();
-        assert_eq!(declaration, "my_method");
+    fn test_class_kinds() {
+        let models = lex(concat!(
+            "class A(pydantic.BaseModel): ...\n",
+            "class B(RootModel[list[int]]): ...\n",
+            "@dataclasses.dataclass(frozen=True)\n",
+            "class C: ...\n",
+            "class D(enum.IntEnum): ...\n",
+            "class E(A): ...\n",
+            "@attr.s(auto_attribs=True)\n",
+            "class F: ...\n",
+            "@define\n",
+            "class G: ...\n",
+            "class H(msgspec.Struct): ...\n",
+            "class I(typing.TypedDict, total=False): ...\n",
+            "class J(NamedTuple): ...\n",
+            "class K(Struct): ...\n",
+        )
+        .to_string())
+        .unwrap();
+        let kinds = models.iter().map(|m| m.kind()).collect::<Vec<_>>();
+        use PyClassKind::*;
+        assert_eq!(
+            kinds,
+            vec![
+                Model, Model, Dataclass, Enum, Plain, Dataclass, Plain, Struct, TypedDict,
+                NamedTuple, Plain
+            ]
+        );
+
+        // Decorators and bases are resolved through imports.
+        let models = lex(concat!(
+            "from attrs import define\n",
+            "from msgspec import Struct\n",
+            "@define\n",
+            "class G: ...\n",
+            "class K(Struct): ...\n",
+        )
+        .to_string())
+        .unwrap();
+        assert_eq!(models[0].kind(), Dataclass);
+        assert_eq!(models[1].kind(), Struct);
+    }
+
+    #[test]
+    fn test_lex_file() {
+        let root = std::env::temp_dir().join(format!("nereus-lex-{}", std::process::id()));
+        let package = root.join("shop").join("orders");
+        fs::create_dir_all(&package).unwrap();
+        fs::write(root.join("shop").join("__init__.py"), "").unwrap();
+        fs::write(package.join("__init__.py"), "class Base: ...\n").unwrap();
+        fs::write(
+            package.join("models.py"),
+            concat!(
+                "from . import Base as B\n",
+                "class Order(B):\n",
+                "    class Line: ...\n",
+                "    line: Line\n",
+                "    for i in range(3): ...\n",
+            ),
+        )
+        .unwrap();
+
+        assert_eq!(module_name(&package.join("__init__.py")), "shop.orders");
+        let (models, diagnostics) = lex_file(&package.join("models.py")).unwrap();
+        let order = &models[0];
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Severity::Note);
+        assert_eq!(
+            diagnostics[0].file.as_deref(),
+            Some(package.join("models.py").as_path())
+        );
+        assert_eq!(order.module.as_deref(), Some("shop.orders.models"));
+        assert_eq!(
+            order.file.as_deref(),
+            Some(package.join("models.py").as_path())
+        );
+        assert_eq!(order.span.line, 2);
+        assert_eq!(order.qualify("B"), "shop.orders.Base");
+        assert_eq!(order.qualify("Order.Line"), "shop.orders.models.Order.Line");
+        assert_eq!(
+            order.children[0].module.as_deref(),
+            Some("shop.orders.models")
+        );
+        // The walked root is not a package itself.
+        fs::write(root.join("main.py"), "").unwrap();
+        assert_eq!(module_name(&root.join("main.py")), "main");
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_lex_lenient() {
+        let (models, diagnostics) =
+            lex_lenient("class A:\n    x = [1, 2)\n    y: int\nclass B: ...\n".to_string());
+        assert_eq!(models.len(), 2);
+        assert_eq!(models[0].props[0].name, "y");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert_eq!(diagnostics[0].snippet.as_ref().unwrap().span.line, 2);
+    }
+
+    #[test]
+    fn test_qualify() {
+        let models = lex(concat!(
+            "import pydantic as pd, typing\n",
+            "from pydantic import BaseModel as Model, Field\n",
+            "from ...pkg import *\n",
+            "class A(Model): ...\n",
+        )
+        .to_string())
+        .unwrap();
+        let a = &models[0];
+        assert_eq!(
+            a.qualify("pd.fields.FieldInfo"),
+            "pydantic.fields.FieldInfo"
+        );
+        assert_eq!(a.qualify("typing.Optional"), "typing.Optional");
+        assert_eq!(a.qualify("Field"), "pydantic.Field");
+        assert_eq!(a.qualify("int"), "int");
+        // Without a module, local classes stay unqualified.
+        assert_eq!(a.qualify("A"), "A");
+        assert_eq!(a.kind(), PyClassKind::Model);
+    }
+
+    #[test]
+    fn test_model_config() {
+        let models = lex(concat!(
+            "class Model(pydantic.BaseModel):\n",
+            "    model_config = ConfigDict(frozen=True, extra='forbid')\n",
+            "class Other(pydantic.BaseModel):\n",
+            "    model_config: ConfigDict = {'strict': True}\n",
+        )
+        .to_string())
+        .unwrap();
+        assert!(models[0].props.is_empty());
+        let keys = models[0]
+            .config
+            .iter()
+            .map(|c| c.key.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(keys, vec!["frozen", "extra"]);
+        assert_eq!(models[0].config[1].value.text, "'forbid'");
+        assert_eq!(models[1].config[0].key, "strict");
+    }
+
+    #[test]
+    fn test_enum_members() {
+        let models = lex(concat!(
+            "class Color(enum.StrEnum):\n",
+            "    RED = 'red'\n",
+            "    GREEN = auto()\n",
+            "    BLUE\n",
+            "    _ignore_ = ['TMP']\n",
+            "    label: str\n",
+            "    def describe(self) -> str: ...\n",
+            "class NotEnum:\n",
+            "    RED = 'red'\n",
+        )
+        .to_string())
+        .unwrap();
+        let members = models[0]
+            .members
+            .iter()
+            .map(|member| member.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(members, ["RED = 'red'", "GREEN = auto()", "BLUE"]);
+        let props = models[0].props.iter().map(|p| p.name.as_str());
+        assert_eq!(props.collect::<Vec<_>>(), ["_ignore_", "label"]);
+        assert_eq!(models[0].methods.len(), 1);
+        assert!(models[1].members.is_empty());
+        assert_eq!(models[1].props.len(), 1);
+    }
+
+    #[test]
+    fn test_class_options() {
+        let models = lex(concat!(
+            "@register(name='point')\n",
+            "@dataclass(frozen=True, slots=True)\n",
+            "class Point: ...\n",
+            "class Tag(msgspec.Struct, frozen=True, metaclass=Meta): ...\n",
+            "class Model(pydantic.BaseModel, extra='forbid'):\n",
+            "    model_config = ConfigDict(frozen=True)\n",
+        )
+        .to_string())
+        .unwrap();
+        let keys = |model: &PyClass| {
+            model
+                .config
+                .iter()
+                .map(|c| format!("{}={}", c.key, c.value))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(keys(&models[0]), ["frozen=True", "slots=True"]);
+        assert_eq!(keys(&models[1]), ["frozen=True"]);
+        assert_eq!(keys(&models[2]), ["extra='forbid'", "frozen=True"]);
     }
 }
@@ -0,0 +1,549 @@
+use crate::diagnostic::ScanError;
+
+/// Operators and delimiters, longest first so that the first match wins.
+const OPERATORS: [&str; 47] = [
+    "**=", "//=", ">>=", "<<=", "...", "->", ":=", "==", "!=", "<=", ">=", "**", "//", "<<", ">>",
+    "+=", "-=", "*=", "/=", "%=", "&=", "|=", "^=", "@=", "(", ")", "[", "]", "{", "}", ",", ":",
+    ";", ".", "+", "-", "*", "/", "%", "|", "&", "^", "~", "<", ">", "=", "@",
+];
+
+/// Tab stops are every eight columns, as in CPython.
+const TABSIZE: usize = 8;
+
+const STRING_PREFIXES: [&str; 12] = [
+    "rb", "br", "rf", "fr", "rt", "tr", "r", "b", "f", "u", "t", "",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    Name,
+    Number,
+    Str,
+    Op,
+    /// End of a logical line.
+    Newline,
+    /// Start of a block indented deeper than the enclosing one.
+    Indent,
+    /// End of an indented block.
+    Dedent,
+    EndMarker,
+}
+
+/// Location of a token in the source: byte offsets as well as the
+/// (1-based) line and (0-based) column at which it starts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub col: usize,
+}
+
+impl Span {
+    /// Returns the span covering both `self` and a later span `other`.
+    pub fn to(self, other: Span) -> Span {
+        Span {
+            end: other.end,
+            ..self
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub text: String,
+    pub span: Span,
+}
+
+impl Token {
+    pub fn is_op(&self, op: &str) -> bool {
+        self.kind == TokenKind::Op && self.text == op
+    }
+
+    pub fn is_name(&self, name: &str) -> bool {
+        self.kind == TokenKind::Name && self.text == name
+    }
+
+    fn end_line(&self) -> usize {
+        self.span.line + self.text.matches('\n').count()
+    }
+}
+
+struct Cursor<'a> {
+    source: &'a str,
+    pos: usize,
+    line: usize,
+    /// Column of `pos` in characters, kept up to date by `bump`.
+    col: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn rest(&self) -> &'a str {
+        &self.source[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn peek_nth(&self, n: usize) -> Option<char> {
+        self.rest().chars().nth(n)
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let ch = self.peek()?;
+        self.pos += ch.len_utf8();
+        if ch == '\n' {
+            self.line += 1;
+            self.col = 0;
+        } else {
+            self.col += 1;
+        }
+        Some(ch)
+    }
+
+    fn eat_while(&mut self, pred: impl Fn(char) -> bool) {
+        while self.peek().is_some_and(&pred) {
+            self.bump();
+        }
+    }
+}
+
+/// Splits Python source into a stream of tokens.
+///
+/// Comments, blank lines and newlines inside brackets or after an explicit
+/// line continuation (`\`) are dropped, so that every `Newline` token marks
+/// the end of a logical line. Changes in indentation are reported through
+/// `Indent` and `Dedent` tokens, whatever mix of spaces and tabs is used.
+pub fn tokenize(source: &str) -> Result<Vec<Token>, ScanError> {
+    let mut tokens: Vec<Token> = vec![];
+    let mut brackets: Vec<(char, Span)> = vec![];
+    let mut indents = vec![Indentation::default()];
+    let mut at_line_start = true;
+    let mut cursor = Cursor {
+        source,
+        pos: 0,
+        line: 1,
+        col: 0,
+    };
+
+    while let Some(ch) = cursor.peek() {
+        if at_line_start {
+            at_line_start = false;
+            scan_indentation(&mut cursor, &mut indents, &mut tokens)?;
+            continue;
+        }
+
+        let start = cursor.pos;
+        let line = cursor.line;
+        let col = cursor.col;
+        let span_from = |cursor: &Cursor| Span {
+            start,
+            end: cursor.pos,
+            line,
+            col,
+        };
+
+        if ch == '\n' {
+            cursor.bump();
+            let ends_line = tokens.last().is_some_and(|t| t.kind != TokenKind::Newline);
+            if brackets.is_empty() && ends_line {
+                tokens.push(Token {
+                    kind: TokenKind::Newline,
+                    text: String::new(),
+                    span: span_from(&cursor),
+                });
+            }
+            at_line_start = brackets.is_empty();
+        } else if ch.is_whitespace() {
+            cursor.bump();
+        } else if ch == '#' {
+            cursor.eat_while(|c| c != '\n');
+        } else if ch == '\\' {
+            cursor.bump();
+            if cursor.peek() == Some('\r') {
+                cursor.bump();
+            }
+            if cursor.bump() != Some('\n') {
+                return Err(ScanError::InvalidContinuation {
+                    span: span_from(&cursor),
+                });
+            }
+        } else if let Some(prefix_len) = string_prefix(cursor.rest()) {
+            for _ in 0..prefix_len {
+                cursor.bump();
+            }
+            let prefix = span_from(&cursor);
+            scan_string(&mut cursor, prefix)?;
+            tokens.push(Token {
+                kind: TokenKind::Str,
+                text: source[start..cursor.pos].to_string(),
+                span: span_from(&cursor),
+            });
+        } else if ch.is_alphabetic() || ch == '_' {
+            cursor.eat_while(|c| c.is_alphanumeric() || c == '_');
+            tokens.push(Token {
+                kind: TokenKind::Name,
+                text: source[start..cursor.pos].to_string(),
+                span: span_from(&cursor),
+            });
+        } else if ch.is_ascii_digit()
+            || (ch == '.' && cursor.peek_nth(1).is_some_and(|c| c.is_ascii_digit()))
+        {
+            scan_number(&mut cursor);
+            tokens.push(Token {
+                kind: TokenKind::Number,
+                text: source[start..cursor.pos].to_string(),
+                span: span_from(&cursor),
+            });
+        } else if let Some(op) = OPERATORS.iter().find(|op| cursor.rest().starts_with(**op)) {
+            for _ in 0..op.len() {
+                cursor.bump();
+            }
+            let span = span_from(&cursor);
+            match ch {
+                '(' | '[' | '{' => brackets.push((ch, span)),
+                ')' | ']' | '}' => match brackets.pop() {
+                    Some((open, _)) if closing(open) == ch => {}
+                    _ => return Err(ScanError::UnmatchedBracket { close: ch, span }),
+                },
+                _ => {}
+            }
+            tokens.push(Token {
+                kind: TokenKind::Op,
+                text: op.to_string(),
+                span,
+            });
+        } else {
+            cursor.bump();
+            return Err(ScanError::UnexpectedCharacter {
+                ch,
+                span: span_from(&cursor),
+            });
+        }
+    }
+
+    if let Some((open, span)) = brackets.pop() {
+        return Err(ScanError::UnterminatedBracket { open, span });
+    }
+
+    let end = Span {
+        start: source.len(),
+        end: source.len(),
+        line: cursor.line,
+        col: cursor.col,
+    };
+    if tokens.last().is_some_and(|t| t.kind != TokenKind::Newline) {
+        tokens.push(Token {
+            kind: TokenKind::Newline,
+            text: String::new(),
+            span: end,
+        });
+    }
+    for _ in 1..indents.len() {
+        tokens.push(Token {
+            kind: TokenKind::Dedent,
+            text: String::new(),
+            span: end,
+        });
+    }
+    tokens.push(Token {
+        kind: TokenKind::EndMarker,
+        text: String::new(),
+        span: end,
+    });
+    Ok(tokens)
+}
+
+/// Indentation of a block, measured both with tabs expanded to the next tab
+/// stop and with tabs counted as a single column. A change in indentation
+/// must compare the same way under both measures, otherwise it depends on
+/// the tab size and is rejected (see CPython's `TabError`).
+#[derive(Debug, Default, Clone, Copy)]
+struct Indentation {
+    col: usize,
+    alt_col: usize,
+}
+
+/// Measures the indentation at the start of a line and emits `Indent` or
+/// `Dedent` tokens for any change from the enclosing block.
+fn scan_indentation(
+    cursor: &mut Cursor,
+    indents: &mut Vec<Indentation>,
+    tokens: &mut Vec<Token>,
+) -> Result<(), ScanError> {
+    let start = cursor.pos;
+    let line = cursor.line;
+    let mut current = Indentation::default();
+    loop {
+        match cursor.peek() {
+            Some(' ') => {
+                current.col += 1;
+                current.alt_col += 1;
+            }
+            Some('\t') => {
+                current.col = (current.col / TABSIZE + 1) * TABSIZE;
+                current.alt_col += 1;
+            }
+            Some('\x0c') => current = Indentation::default(),
+            _ => break,
+        }
+        cursor.bump();
+    }
+
+    // Blank lines and comments don't affect indentation.
+    if matches!(cursor.peek(), None | Some('\n' | '\r' | '#')) {
+        return Ok(());
+    }
+
+    let span = Span {
+        start,
+        end: cursor.pos,
+        line,
+        col: 0,
+    };
+    let inconsistent = || ScanError::InconsistentIndentation { span };
+    let outer = *indents.last().unwrap();
+    if current.col > outer.col {
+        if current.alt_col <= outer.alt_col {
+            return Err(inconsistent());
+        }
+        indents.push(current);
+        tokens.push(Token {
+            kind: TokenKind::Indent,
+            text: cursor.source[start..cursor.pos].to_string(),
+            span,
+        });
+        return Ok(());
+    }
+
+    while current.col < indents.last().unwrap().col {
+        indents.pop();
+        tokens.push(Token {
+            kind: TokenKind::Dedent,
+            text: String::new(),
+            span,
+        });
+    }
+    let outer = *indents.last().unwrap();
+    if current.col != outer.col {
+        return Err(ScanError::InvalidDedent { span });
+    }
+    if current.alt_col != outer.alt_col {
+        return Err(inconsistent());
+    }
+    Ok(())
+}
+
+/// Reassembles tokens into source text, collapsing whitespace between
+/// tokens to a single space and dropping line breaks inside brackets.
+pub fn untokenize(tokens: &[Token]) -> String {
+    let mut text = String::new();
+    for (i, token) in tokens.iter().enumerate() {
+        if i > 0 {
+            let prev = &tokens[i - 1];
+            if prev.end_line() != token.span.line {
+                if prev.is_op(",") && !matches!(token.text.as_str(), ")" | "]" | "}") {
+                    text.push(' ');
+                }
+            } else if prev.span.end < token.span.start {
+                text.push(' ');
+            }
+        }
+        text.push_str(&token.text);
+    }
+    text
+}
+
+fn closing(open: char) -> char {
+    match open {
+        '(' => ')',
+        '[' => ']',
+        _ => '}',
+    }
+}
+
+/// Returns the length of the string prefix (e.g. `rb`) if a string literal
+/// starts at the beginning of `rest`.
+fn string_prefix(rest: &str) -> Option<usize> {
+    STRING_PREFIXES.iter().find_map(|prefix| {
+        let candidate = rest.get(..prefix.len())?;
+        let quote = rest[prefix.len()..].chars().next()?;
+        (candidate.eq_ignore_ascii_case(prefix) && (quote == '"' || quote == '\''))
+            .then_some(prefix.len())
+    })
+}
+
+/// Scans a string literal past its opening quote, given the span of its
+/// prefix so that an unterminated literal can be reported from its start.
+fn scan_string(cursor: &mut Cursor, start: Span) -> Result<(), ScanError> {
+    let quote = cursor.bump().unwrap();
+    let triple = cursor.peek() == Some(quote) && cursor.peek_nth(1) == Some(quote);
+    if triple {
+        cursor.bump();
+        cursor.bump();
+    }
+
+    loop {
+        match cursor.bump() {
+            Some('\\') => {
+                cursor.bump();
+            }
+            Some('\n') if !triple => break,
+            Some(ch) if ch == quote => {
+                if !triple {
+                    return Ok(());
+                }
+                if cursor.peek() == Some(quote) && cursor.peek_nth(1) == Some(quote) {
+                    cursor.bump();
+                    cursor.bump();
+                    return Ok(());
+                }
+            }
+            Some(_) => {}
+            None => break,
+        }
+    }
+    let span = Span {
+        end: cursor.pos,
+        ..start
+    };
+    Err(if triple {
+        ScanError::UnterminatedDocstring { span }
+    } else {
+        ScanError::UnterminatedString { span }
+    })
+}
+
+fn scan_number(cursor: &mut Cursor) {
+    let radix = cursor.peek() == Some('0')
+        && matches!(cursor.peek_nth(1), Some('x' | 'X' | 'o' | 'O' | 'b' | 'B'));
+    if radix {
+        cursor.eat_while(|c| c.is_alphanumeric() || c == '_');
+        return;
+    }
+    loop {
+        match cursor.peek() {
+            Some('e' | 'E') => {
+                cursor.bump();
+                if matches!(cursor.peek(), Some('+' | '-')) {
+                    cursor.bump();
+                }
+            }
+            Some(c) if c.is_alphanumeric() || c == '_' || c == '.' => {
+                cursor.bump();
+            }
+            _ => break,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn texts(source: &str) -> Vec<String> {
+        tokenize(source)
+            .unwrap()
+            .into_iter()
+            .filter(|t| !matches!(t.kind, TokenKind::Newline | TokenKind::EndMarker))
+            .map(|t| t.text)
+            .collect()
+    }
+
+    #[test]
+    fn test_string_with_colon() {
+        assert_eq!(
+            texts("x: str = 'a: b'"),
+            vec!["x", ":", "str", "=", "'a: b'"]
+        );
+    }
+
+    #[test]
+    fn test_prefixed_and_triple_quoted_strings() {
+        let source = "a = rb'\\x00' + f\"{x!r}\" + '''one\n'two'\n'''";
+        assert_eq!(
+            texts(source),
+            vec![
+                "a",
+                "=",
+                "rb'\\x00'",
+                "+",
+                "f\"{x!r}\"",
+                "+",
+                "'''one\n'two'\n'''"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_comments_and_blank_lines() {
+        let tokens = tokenize("x = 1  # a comment\n\n\n# another\ny = 2\n").unwrap();
+        let newlines = tokens
+            .iter()
+            .filter(|t| t.kind == TokenKind::Newline)
+            .count();
+        assert_eq!(newlines, 2);
+    }
+
+    #[test]
+    fn test_brackets_and_continuations_join_lines() {
+        let tokens = tokenize("x = {\n    'a': 1,\n}\ny = 1 + \\\n    2\n").unwrap();
+        let newlines = tokens
+            .iter()
+            .filter(|t| t.kind == TokenKind::Newline)
+            .map(|t| t.span.line)
+            .collect::<Vec<_>>();
+        assert_eq!(newlines, vec![3, 5]);
+    }
+
+    #[test]
+    fn test_unterminated_string() {
+        assert!(tokenize("x = 'abc\n").is_err());
+        assert!(tokenize("x = \"\"\"abc\n").is_err());
+    }
+
+    #[test]
+    fn test_unmatched_brackets() {
+        assert!(tokenize("x = (1, 2\n").is_err());
+        assert!(tokenize("x = [1, 2)\n").is_err());
+    }
+
+    fn indentation(source: &str) -> Vec<TokenKind> {
+        tokenize(source)
+            .unwrap()
+            .into_iter()
+            .map(|t| t.kind)
+            .filter(|k| matches!(k, TokenKind::Indent | TokenKind::Dedent))
+            .collect()
+    }
+
+    #[test]
+    fn test_indentation_styles() {
+        use TokenKind::{Dedent, Indent};
+        let expected = vec![Indent, Indent, Dedent, Dedent];
+        assert_eq!(indentation("a:\n  b:\n    c\nd\n"), expected);
+        assert_eq!(indentation("a:\n\tb:\n\t\tc\nd\n"), expected);
+        assert_eq!(
+            indentation("a:\n    b:\n\n  # comment\n        c\nd\n"),
+            expected
+        );
+        assert_eq!(indentation("a:\n    b:\n        c\n"), expected);
+    }
+
+    #[test]
+    fn test_invalid_indentation() {
+        assert!(tokenize("a:\n    b\n  c\n").is_err());
+        assert!(tokenize("a:\n        b\n\tc\n").is_err());
+    }
+
+    #[test]
+    fn test_untokenize() {
+        let tokens = tokenize("f(\n    a,\n    b=list[str | None],\n)").unwrap();
+        assert_eq!(
+            untokenize(&tokens[..tokens.len() - 2]),
+            "f(a, b=list[str | None],)"
+        );
+    }
+}
@@ -1,18 +1,225 @@
-use std::{fs, io, path::Path};
-
-pub fn read_files(dir: &Path, src: Option<String>) -> Result<String, io::Error> {
-    let mut src = src.unwrap_or(String::new());
-    if dir.is_dir() {
-        for entry in fs::read_dir(dir)? {
-            let entry = entry?;
-            let path = entry.path();
-            if path.is_dir() {
-                src = read_files(&path, Option::Some(src))?;
+use crate::glob::Glob;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Directories that are never scanned, in addition to hidden ones.
+const SKIPPED_DIRS: [&str; 2] = [".venv", "__pycache__"];
+
+/// Selects the files to scan when walking a directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileFilter {
+    /// Files are scanned if they match any of these patterns.
+    pub include: Vec<Glob>,
+    /// Files and directories matching any of these patterns are skipped.
+    pub exclude: Vec<Glob>,
+    /// Skip files and directories listed in `.gitignore` files.
+    pub gitignore: bool,
+}
+
+impl Default for FileFilter {
+    fn default() -> Self {
+        FileFilter {
+            include: vec![Glob::new("*.py")],
+            exclude: vec![],
+            gitignore: false,
+        }
+    }
+}
+
+/// A pattern from a `.gitignore` file.
+#[derive(Debug, Clone)]
+struct IgnoreRule {
+    glob: Glob,
+    /// Re-includes paths excluded by an earlier rule (`!pattern`).
+    negated: bool,
+    /// Only applies to directories (`pattern/`).
+    dir_only: bool,
+}
+
+/// Lists the files to scan, in a stable order. A path to a single file is
+/// returned as is, whether or not it matches the filter.
+pub fn find_files(path: &Path, filter: &FileFilter) -> Result<Vec<PathBuf>, io::Error> {
+    if !fs::metadata(path)?.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let mut files = vec![];
+    walk(path, path, filter, &[], &mut files)?;
+    Ok(files)
+}
+
+fn walk(
+    root: &Path,
+    dir: &Path,
+    filter: &FileFilter,
+    ignores: &[IgnoreRule],
+    files: &mut Vec<PathBuf>,
+) -> Result<(), io::Error> {
+    let mut ignores = ignores.to_vec();
+    if filter.gitignore {
+        if let Ok(contents) = fs::read_to_string(dir.join(".gitignore")) {
+            ignores.extend(parse_gitignore(&contents, &relative(root, dir)));
+        }
+    }
+    let mut entries = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<PathBuf>, io::Error>>()?;
+    entries.sort();
+    for path in entries {
+        let rel = relative(root, &path);
+        let is_dir = path.is_dir();
+        if is_ignored(&ignores, &rel, is_dir) || filter.exclude.iter().any(|g| g.matches(&rel)) {
+            continue;
+        }
+        if is_dir {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            if !name.starts_with('.') && !SKIPPED_DIRS.contains(&name.as_ref()) {
+                walk(root, &path, filter, &ignores, files)?;
+            }
+        } else if filter.include.iter().any(|g| g.matches(&rel)) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Returns `path` relative to `root` with `/` separators, as matched by globs.
+fn relative(root: &Path, path: &Path) -> String {
+    let rel = path.strip_prefix(root).unwrap_or(path);
+    rel.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Parses the rules of a `.gitignore` file in the directory `base`, given
+/// relative to the walked root.
+fn parse_gitignore(contents: &str, base: &str) -> Vec<IgnoreRule> {
+    let mut rules = vec![];
+    for line in contents.lines() {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (negated, pattern) = match line.strip_prefix('!') {
+            Some(pattern) => (true, pattern),
+            None => (false, line),
+        };
+        let (dir_only, pattern) = match pattern.strip_suffix('/') {
+            Some(pattern) => (true, pattern),
+            None => (false, pattern),
+        };
+        // Patterns containing a slash are relative to the `.gitignore` file;
+        // the others match a file name at any depth below it.
+        let glob = if pattern.contains('/') {
+            let pattern = pattern.trim_start_matches('/');
+            if base.is_empty() {
+                Glob::anchored(pattern)
             } else {
-                let contents = fs::read_to_string(entry.path())?;
-                src.push_str(&contents);
+                Glob::anchored(&format!("{}/{}", base, pattern))
             }
+        } else {
+            Glob::new(pattern)
+        };
+        rules.push(IgnoreRule {
+            glob,
+            negated,
+            dir_only,
+        });
+    }
+    rules
+}
+
+/// Applies `.gitignore` rules in order; the last matching rule wins.
+fn is_ignored(rules: &[IgnoreRule], path: &str, is_dir: bool) -> bool {
+    let mut ignored = false;
+    for rule in rules.iter() {
+        if (is_dir || !rule.dir_only) && rule.glob.matches(path) {
+            ignored = !rule.negated;
+        }
+    }
+    ignored
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn touch(root: &Path, files: &[(&str, &str)]) {
+        for (file, contents) in files.iter() {
+            let path = root.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
         }
     }
-    Ok(src)
+
+    fn found(root: &Path, filter: &FileFilter) -> Vec<String> {
+        find_files(root, filter)
+            .unwrap()
+            .iter()
+            .map(|path| relative(root, path))
+            .collect()
+    }
+
+    #[test]
+    fn test_find_files() {
+        let root = std::env::temp_dir().join(format!("nereus-walk-{}", std::process::id()));
+        touch(
+            &root,
+            &[
+                ("models.py", ""),
+                ("models.pyc", ""),
+                ("notes.txt", ""),
+                ("__pycache__/models.py", ""),
+                (".venv/lib/site.py", ""),
+                (".hidden/secret.py", ""),
+                ("shop/orders.py", ""),
+                ("shop/tests/test_orders.py", ""),
+                ("build/generated.py", ""),
+                (".gitignore", "build/\n*.tmp.py\n"),
+                ("shop/.gitignore", "/tests\n!keep.tmp.py\n"),
+                ("shop/keep.tmp.py", ""),
+                ("drop.tmp.py", ""),
+            ],
+        );
+
+        let all = found(&root, &FileFilter::default());
+        assert_eq!(
+            all,
+            [
+                "build/generated.py",
+                "drop.tmp.py",
+                "models.py",
+                "shop/keep.tmp.py",
+                "shop/orders.py",
+                "shop/tests/test_orders.py",
+            ]
+        );
+
+        let filter = FileFilter {
+            gitignore: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            found(&root, &filter),
+            ["models.py", "shop/keep.tmp.py", "shop/orders.py"]
+        );
+
+        let filter = FileFilter {
+            include: vec![Glob::new("*.py"), Glob::new("*.txt")],
+            exclude: vec![Glob::new("shop"), Glob::new("build/*")],
+            gitignore: false,
+        };
+        assert_eq!(
+            found(&root, &filter),
+            ["drop.tmp.py", "models.py", "notes.txt"]
+        );
+
+        // A single file is scanned whatever its name.
+        let notes = root.join("notes.txt");
+        assert_eq!(find_files(&notes, &filter).unwrap(), [notes]);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
 }